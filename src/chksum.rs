@@ -3,7 +3,8 @@
 //! This module provides a checksum type based on the DJB2 hash algorithm. The checksum
 //! uses only 31 bits, with the most significant bit reserved as a validity marker.
 //! This allows quick detection of uninitialized or invalid slots by checking if the
-//! first byte has the high bit set.
+//! first byte has the high bit set. For flash that doesn't read erased bytes as 0xFF,
+//! the storage encodes headers so erased bytes still decode as 0xFF.
 
 /// A 31-bit checksum with validity marker
 ///
@@ -42,9 +43,9 @@ impl Chksum {
     /// * `prev` - The checksum of the previous savegame
     /// * `data` - The data to hash
     pub const fn hash(prev: Chksum, data: &[u8]) -> Self {
-        let hash = djb2::hash(&prev.to_bytes());
-        let hash = djb2::hash_with_initial(hash, data);
-        Self(hash & CHKSUM_MASK)
+        let mut hasher = Hasher::new(prev);
+        hasher.update(data);
+        hasher.finish()
     }
 
     /// Check if this checksum has a valid format
@@ -67,6 +68,29 @@ impl Chksum {
     }
 }
 
+/// Incremental checksum computation
+///
+/// Computes the same checksum as [`Chksum::hash`] for data fed in chunks.
+#[derive(Debug, Clone)]
+pub struct Hasher(u32);
+
+impl Hasher {
+    /// Start a checksum chained with a previous checksum
+    pub const fn new(prev: Chksum) -> Self {
+        Self(djb2::hash(&prev.to_bytes()))
+    }
+
+    /// Add the next chunk of data
+    pub const fn update(&mut self, data: &[u8]) {
+        self.0 = djb2::hash_with_initial(self.0, data);
+    }
+
+    /// Get the checksum of the data so far
+    pub const fn finish(&self) -> Chksum {
+        Chksum(self.0 & CHKSUM_MASK)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,6 +103,17 @@ mod tests {
         assert!(chksum.is_valid());
     }
 
+    #[test]
+    fn test_hasher() {
+        let mut hasher = Hasher::new(Chksum::zero());
+        hasher.update(b"hello ");
+        hasher.update(b"world");
+        assert_eq!(
+            hasher.finish(),
+            Chksum::hash(Chksum::zero(), b"hello world")
+        );
+    }
+
     #[test]
     fn test_header_mask() {
         let chksum = Chksum(0xFFFFFFFF);
@@ -0,0 +1,97 @@
+//! Savegame compression
+//!
+//! This module provides the compression of savegames written with
+//! [`Storage::write_compressed`](crate::storage::Storage::write_compressed). Available with the
+//! `compress` feature.
+//!
+//! Savegames are compressed with the LZ4 block format, prefixed with the uncompressed length
+//! (4 bytes, big endian). The slot header has [`Slot::FLAG_COMPRESSED`](crate::Slot::FLAG_COMPRESSED)
+//! set and its length field holds the compressed size. Savegames that don't get smaller are
+//! stored uncompressed, so compression never takes more space.
+//!
+//! [`Storage::read`](crate::storage::Storage::read) decompresses savegames transparently. The
+//! compressed data is read into the end of the buffer and decompressed into its start, so the
+//! buffer must hold both.
+
+/// Size of the uncompressed length stored in front of the compressed data in bytes
+pub const LENGTH_SIZE: usize = 4;
+
+/// Size of the buffer needed to compress `len` bytes of savegame data
+///
+/// The compressor needs room for the worst case, which is larger than the uncompressed
+/// data. Can be used for the size of a static scratch buffer.
+pub const fn max_size(len: usize) -> usize {
+    LENGTH_SIZE + lz4_flex::block::get_maximum_output_size(len)
+}
+
+/// Compress savegame data into `out`, returns the compressed size
+///
+/// Returns `None` if `out` is smaller than [`max_size`] or the compressed data isn't
+/// smaller than `data`, the savegame should be stored uncompressed then.
+pub(crate) fn compress(data: &[u8], out: &mut [u8]) -> Option<usize> {
+    let len = u32::try_from(data.len()).ok()?;
+    let (header, block) = out.split_at_mut_checked(LENGTH_SIZE)?;
+    let size = lz4_flex::block::compress_into(data, block).ok()?;
+    header.copy_from_slice(&len.to_be_bytes());
+
+    let size = LENGTH_SIZE.saturating_add(size);
+    (size < data.len()).then_some(size)
+}
+
+/// Decompress savegame data that was compressed with [`compress`] into `out`
+///
+/// Returns the decompressed data, or `None` if the data is corrupted or doesn't fit
+/// into `out`.
+pub(crate) fn decompress<'a>(data: &[u8], out: &'a mut [u8]) -> Option<&'a mut [u8]> {
+    let (header, block) = data.split_first_chunk::<LENGTH_SIZE>()?;
+    let len = u32::from_be_bytes(*header) as usize;
+    let out = out.get_mut(..len)?;
+    let size = lz4_flex::block::decompress_into(block, out).ok()?;
+    (size == len).then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_decompress() {
+        let mut data = [0u8; 256];
+        data[..5].copy_from_slice(b"hello");
+        data[200] = 42;
+
+        let mut compressed = [0u8; max_size(256)];
+        let size = compress(&data, &mut compressed).unwrap();
+        assert!(size < 32);
+
+        let mut out = [0u8; 256];
+        assert_eq!(decompress(&compressed[..size], &mut out).unwrap(), &data);
+
+        // Doesn't fit into a smaller buffer
+        let mut out = [0u8; 255];
+        assert_eq!(decompress(&compressed[..size], &mut out), None);
+    }
+
+    #[test]
+    fn test_compress_incompressible() {
+        let data = *b"hello world";
+        let mut compressed = [0u8; max_size(256)];
+        assert_eq!(compress(&data, &mut compressed), None);
+
+        // The output buffer is too small
+        let data = [0u8; 256];
+        assert_eq!(compress(&data, &mut compressed[..256]), None);
+    }
+
+    #[test]
+    fn test_decompress_corrupted() {
+        let data = [0u8; 256];
+        let mut compressed = [0u8; max_size(256)];
+        let size = compress(&data, &mut compressed).unwrap();
+
+        // Truncated data
+        let mut out = [0u8; 256];
+        assert_eq!(decompress(&compressed[..size - 1], &mut out), None);
+        assert_eq!(decompress(&compressed[..2], &mut out), None);
+    }
+}
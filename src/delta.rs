@@ -0,0 +1,276 @@
+//! Delta-encoded savegames
+//!
+//! This module provides the patch format of savegames written with
+//! [`Storage::append_delta`](crate::storage::Storage::append_delta). Available with the `delta`
+//! feature.
+//!
+//! A delta savegame only stores the XOR of the new savegame with the previous one, its slot
+//! header has [`Slot::FLAG_DELTA`](crate::Slot::FLAG_DELTA) set. Every few savegames a full
+//! savegame is written as a checkpoint. [`Storage::read`](crate::storage::Storage::read)
+//! follows the checksum chain from a delta savegame back to its checkpoint and XORs them all
+//! together, so the patches are applied in the reverse order they were written.
+//!
+//! The patch starts with the length of the new savegame (4 bytes, big endian) and its checksum
+//! (4 bytes) to verify the reconstructed data. It's followed by runs of a skip count (2 bytes,
+//! big endian), a byte count (2 bytes, big endian) and that many bytes to XOR, each run
+//! starting behind the previous one. Savegames shorter than others in the chain are treated
+//! as padded with zeros.
+
+use crate::chksum::Chksum;
+
+/// Size of the length and checksum in front of a patch in bytes
+pub const HEADER_SIZE: usize = 4 + Chksum::SIZE;
+
+/// Size of the skip and byte counts in front of a run in bytes
+const RUN_HEADER_SIZE: usize = 4;
+
+/// Default number of delta savegames between two checkpoints
+pub const CHECKPOINT_INTERVAL: usize = 8;
+
+/// Runs separated by at most this many unchanged bytes are merged
+const MERGE_GAP: usize = RUN_HEADER_SIZE;
+
+/// Encode the patch from `base` to `data` into `out`, returns the patch size
+///
+/// Returns `None` if the patch doesn't fit into `out`.
+pub(crate) fn diff(base: &[u8], data: &[u8], out: &mut [u8]) -> Option<usize> {
+    let xor = |i: usize| base.get(i).copied().unwrap_or(0) ^ data.get(i).copied().unwrap_or(0);
+    let end = base.len().max(data.len());
+
+    let mut size = 0usize;
+    let mut push = |bytes: &[u8]| {
+        out.get_mut(size..size.checked_add(bytes.len())?)?
+            .copy_from_slice(bytes);
+        size += bytes.len();
+        Some(())
+    };
+    push(&u32::try_from(data.len()).ok()?.to_be_bytes())?;
+    push(&Chksum::hash(Chksum::zero(), data).to_bytes())?;
+
+    let mut pos = 0;
+    let mut i = 0;
+    while i < end {
+        if xor(i) == 0 {
+            i += 1;
+            continue;
+        }
+
+        // Extend the run until a gap that is worth a new run header
+        let start = i;
+        let mut last = i;
+        while i < end && i - start < usize::from(u16::MAX) && i - last <= MERGE_GAP {
+            if xor(i) != 0 {
+                last = i;
+            }
+            i += 1;
+        }
+
+        let mut skip = start - pos;
+        while skip > usize::from(u16::MAX) {
+            push(&[0xFF, 0xFF, 0, 0])?;
+            skip -= usize::from(u16::MAX);
+        }
+        let count = last + 1 - start;
+        push(&(skip as u16).to_be_bytes())?;
+        push(&(count as u16).to_be_bytes())?;
+        for j in start..=last {
+            push(&[xor(j)])?;
+        }
+
+        pos = last + 1;
+        i = pos;
+    }
+
+    Some(size)
+}
+
+/// XOR `data` into `buf` at `offset`, returns `false` if it doesn't fit
+pub(crate) fn xor_at(buf: &mut [u8], offset: usize, data: &[u8]) -> bool {
+    let Some(buf) = offset
+        .checked_add(data.len())
+        .and_then(|end| buf.get_mut(offset..end))
+    else {
+        return false;
+    };
+    for (byte, xor) in buf.iter_mut().zip(data) {
+        *byte ^= xor;
+    }
+    true
+}
+
+/// Length and checksum of the savegame a patch produces
+#[derive(Debug, PartialEq)]
+pub(crate) struct Header {
+    pub(crate) len: usize,
+    pub(crate) chksum: Chksum,
+}
+
+/// Incremental application of a patch to a buffer
+///
+/// The patch can be fed in chunks of any size, so it can be streamed from flash.
+pub(crate) struct Patch<'a> {
+    buf: &'a mut [u8],
+    header: Option<Header>,
+    /// Partially read patch or run header
+    pending: [u8; HEADER_SIZE],
+    pending_len: usize,
+    /// Bytes left to XOR in the current run
+    remaining: usize,
+    pos: usize,
+    failed: bool,
+}
+
+impl<'a> Patch<'a> {
+    /// Start applying a patch to `buf`
+    pub(crate) const fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            header: None,
+            pending: [0; HEADER_SIZE],
+            pending_len: 0,
+            remaining: 0,
+            pos: 0,
+            failed: false,
+        }
+    }
+
+    /// Apply the next chunk of the patch
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() && !self.failed {
+            if self.remaining > 0 {
+                let take = self.remaining.min(data.len());
+                let (run, rest) = data.split_at(take);
+                self.failed = !xor_at(self.buf, self.pos, run);
+                self.pos = self.pos.saturating_add(take);
+                self.remaining -= take;
+                data = rest;
+                continue;
+            }
+
+            let size = match self.header {
+                None => HEADER_SIZE,
+                Some(_) => RUN_HEADER_SIZE,
+            };
+            let take = (size - self.pending_len).min(data.len());
+            let (head, rest) = data.split_at(take);
+            self.pending[self.pending_len..][..take].copy_from_slice(head);
+            self.pending_len += take;
+            data = rest;
+            if self.pending_len < size {
+                continue;
+            }
+            self.pending_len = 0;
+
+            let [a, b, c, d, e, f, g, h] = self.pending;
+            if self.header.is_none() {
+                self.header = Some(Header {
+                    len: u32::from_be_bytes([a, b, c, d]) as usize,
+                    chksum: Chksum::from_bytes([e, f, g, h]),
+                });
+            } else {
+                let skip = u16::from_be_bytes([a, b]);
+                self.pos = self.pos.saturating_add(usize::from(skip));
+                self.remaining = usize::from(u16::from_be_bytes([c, d]));
+            }
+        }
+    }
+
+    /// Finish applying the patch
+    ///
+    /// Returns the header of the patch, or `None` if the patch was truncated or didn't fit
+    /// into the buffer.
+    pub(crate) fn finish(self) -> Option<Header> {
+        if self.failed || self.pending_len > 0 || self.remaining > 0 {
+            return None;
+        }
+        self.header
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(patch: &[u8], buf: &mut [u8], chunk: usize) -> Option<Header> {
+        let mut applier = Patch::new(buf);
+        for chunk in patch.chunks(chunk) {
+            applier.update(chunk);
+        }
+        applier.finish()
+    }
+
+    #[test]
+    fn test_diff_apply() {
+        let mut base = [0u8; 300];
+        base[..5].copy_from_slice(b"hello");
+        let mut data = base;
+        data[0] = b'j';
+        data[3] = b'p';
+        data[299] = 42;
+
+        let mut patch = [0u8; 64];
+        let size = diff(&base, &data, &mut patch).unwrap();
+        // Two runs, the first two changes are merged
+        assert_eq!(size, HEADER_SIZE + RUN_HEADER_SIZE * 2 + 4 + 1);
+
+        for chunk in [1, 3, size] {
+            let mut buf = base;
+            let header = apply(&patch[..size], &mut buf, chunk).unwrap();
+            assert_eq!(header.len, data.len());
+            assert_eq!(header.chksum, Chksum::hash(Chksum::zero(), &data));
+            assert_eq!(buf, data);
+        }
+    }
+
+    #[test]
+    fn test_diff_length_change() {
+        let base = *b"hello world";
+        let data = *b"hi";
+        let mut patch = [0u8; 64];
+        let size = diff(&base, &data, &mut patch).unwrap();
+
+        let mut buf = base;
+        let header = apply(&patch[..size], &mut buf, 1).unwrap();
+        assert_eq!(header.len, 2);
+        assert_eq!(&buf[..2], b"hi");
+        // The rest of the base is cleared
+        assert_eq!(buf[2..], [0; 9]);
+
+        // Growing again needs a bigger buffer
+        let size = diff(&data, &base, &mut patch).unwrap();
+        let mut buf = [0u8; 2];
+        buf.copy_from_slice(&data);
+        assert_eq!(apply(&patch[..size], &mut buf, 1), None);
+    }
+
+    #[test]
+    fn test_diff_long_skip() {
+        let base = [0u8; 70_000];
+        let mut data = [0u8; 70_000];
+        data[69_999] = 1;
+        let mut patch = [0u8; 64];
+        let size = diff(&base, &data, &mut patch).unwrap();
+        assert_eq!(size, HEADER_SIZE + RUN_HEADER_SIZE * 2 + 1);
+
+        let mut buf = base;
+        assert!(apply(&patch[..size], &mut buf, 7).is_some());
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn test_diff_too_small() {
+        let base = [0u8; 64];
+        let data = [1u8; 64];
+        let mut patch = [0u8; 64];
+        assert_eq!(diff(&base, &data, &mut patch), None);
+    }
+
+    #[test]
+    fn test_patch_truncated() {
+        let mut patch = [0u8; 64];
+        let size = diff(b"hello", b"jello", &mut patch).unwrap();
+        let mut buf = *b"hello";
+        assert_eq!(apply(&patch[..size - 1], &mut buf, 1), None);
+        assert_eq!(apply(&patch[..HEADER_SIZE - 1], &mut buf, 1), None);
+    }
+}
@@ -4,34 +4,197 @@
 //! Available with the `eeprom24x` feature.
 //!
 //! Supports EEPROM chips like AT24C32, AT24C64, AT24C128, etc. using the `eeprom24x` crate's driver.
+//! The driver is wrapped in [`Polled`] together with a strategy that bounds the wait for write
+//! completion.
 
+use crate::poll::{self, Poll};
 use crate::storage::Flash;
 use core::fmt;
 use eeprom24x::Eeprom24xTrait;
 
+/// Errors of the AT24Cxx EEPROM backend
+#[derive(Debug)]
+pub enum Error<E> {
+    /// Error reported by the EEPROM driver
+    Eeprom(eeprom24x::Error<E>),
+    /// The EEPROM didn't complete a write cycle in time
+    Timeout,
+}
+
+impl<E> From<eeprom24x::Error<E>> for Error<E> {
+    fn from(err: eeprom24x::Error<E>) -> Self {
+        Self::Eeprom(err)
+    }
+}
+
+/// AT24Cxx EEPROM with a write completion polling strategy
+///
+/// Write cycles take up to 5ms, so polling should be bounded by time with a
+/// [`Timeout`](crate::poll::Timeout). How long a number of polls takes depends on
+/// the bus clock, a [`MaxAttempts`](crate::poll::MaxAttempts) needs to be sized
+/// for the fastest bus the chip is used on.
+#[derive(Debug)]
+pub struct Polled<T, P> {
+    eeprom: T,
+    poll: P,
+}
+
+impl<T: Eeprom24xTrait, P: Poll> Polled<T, P> {
+    /// Wrap an EEPROM chip with the given polling strategy
+    pub const fn new(eeprom: T, poll: P) -> Self {
+        Self { eeprom, poll }
+    }
+
+    /// Consume the wrapper and return the EEPROM chip and polling strategy
+    pub fn into_inner(self) -> (T, P) {
+        (self.eeprom, self.poll)
+    }
+}
+
+/// Wait for the internal write cycle to complete
+///
+/// The chip doesn't acknowledge its address until the write cycle is done.
+fn wait_ready<T: Eeprom24xTrait, P: Poll>(
+    eeprom: &mut T,
+    poll: &mut P,
+) -> Result<(), Error<T::Error>> {
+    let Ok(ready) = poll::until(poll, || {
+        Ok::<_, core::convert::Infallible>(eeprom.read_current_address().is_ok())
+    });
+    if ready { Ok(()) } else { Err(Error::Timeout) }
+}
+
+fn write<T: Eeprom24xTrait, P: Poll>(
+    eeprom: &mut T,
+    poll: &mut P,
+    mut addr: u32,
+    mut data: &[u8],
+) -> Result<(), Error<T::Error>> {
+    // Writes crossing a page boundary would wrap around within the page
+    let page_size = eeprom.page_size() as u32;
+    while !data.is_empty() {
+        let page_remaining = page_size - addr % page_size;
+        let write_size = data.len().min(page_remaining as usize);
+        let (to_write, remaining) = data.split_at(write_size);
+        eeprom.write_page(addr, to_write)?;
+        wait_ready(eeprom, poll)?;
+        addr = addr.saturating_add(write_size as u32);
+        data = remaining;
+    }
+    Ok(())
+}
+
 /// Flash trait implementation for AT24Cxx EEPROM chips
-impl<T: Eeprom24xTrait> Flash for T
+///
+/// Writes are split on page boundaries of the concrete device, so slots don't
+/// need to be aligned to the EEPROM page size.
+impl<T: Eeprom24xTrait, P: Poll> Flash for Polled<T, P>
 where
     T::Error: fmt::Debug,
 {
-    type Error = eeprom24x::Error<T::Error>;
+    type Error = Error<T::Error>;
 
     fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
-        self.read_data(addr, buf)?;
+        self.eeprom.read_data(addr, buf)?;
         Ok(())
     }
 
     fn write(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Self::Error> {
-        self.write_page(addr, data)?;
-        while self.read_current_address().is_err() {}
-        Ok(())
+        write(&mut self.eeprom, &mut self.poll, addr, data)
+    }
+
+    fn write_slice(&mut self, addr: u32, data: &[u8]) -> Result<(), Self::Error> {
+        write(&mut self.eeprom, &mut self.poll, addr, data)
     }
 
     fn erase(&mut self, addr: u32) -> Result<(), Self::Error> {
-        if self.read_byte(addr)? != 0xFF {
-            self.write_byte(addr, 0xFF)?;
-            while self.read_current_address().is_err() {}
+        if self.eeprom.read_byte(addr)? != 0xFF {
+            self.eeprom.write_byte(addr, 0xFF)?;
+            wait_ready(&mut self.eeprom, &mut self.poll)?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::poll::{MaxAttempts, Timeout};
+    use eeprom24x::{Eeprom24x, SlaveAddr};
+    use embedded_hal::i2c::ErrorKind;
+    use embedded_hal_mock::eh1::delay::{self, CheckedDelay};
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+    use std::vec;
+
+    const ADDR: u8 = 0x50;
+
+    #[test]
+    fn test_write_page_boundary() {
+        let expectations = [
+            Transaction::write(ADDR, vec![0x00, 0x1D, 1, 2, 3]),
+            Transaction::read(ADDR, vec![0]),
+            Transaction::write(ADDR, [vec![0x00, 0x20], vec![4; 32]].concat()),
+            Transaction::read(ADDR, vec![0]),
+            Transaction::write(ADDR, vec![0x00, 0x40, 5]),
+            Transaction::read(ADDR, vec![0]),
+        ];
+        let i2c = Mock::new(&expectations);
+        let eeprom = Eeprom24x::new_24x32(i2c, SlaveAddr::default());
+        let mut eeprom = Polled::new(eeprom, MaxAttempts(3));
+
+        let mut data = [[1, 2, 3].as_slice(), &[4; 32], &[5]].concat();
+        eeprom.write(0x1D, &mut data).unwrap();
+
+        let (eeprom, _poll) = eeprom.into_inner();
+        eeprom.destroy().done();
+    }
+
+    fn busy() -> Transaction {
+        Transaction::read(ADDR, vec![0]).with_error(ErrorKind::Other)
+    }
+
+    #[test]
+    fn test_write_max_attempts() {
+        let expectations = [
+            Transaction::write(ADDR, vec![0x00, 0x00, 1]),
+            busy(),
+            busy(),
+            busy(),
+        ];
+        let i2c = Mock::new(&expectations);
+        let eeprom = Eeprom24x::new_24x32(i2c, SlaveAddr::default());
+        let mut eeprom = Polled::new(eeprom, MaxAttempts(3));
+
+        let err = eeprom.write(0, &mut [1]).unwrap_err();
+        assert!(matches!(err, Error::Timeout));
+
+        let (eeprom, _poll) = eeprom.into_inner();
+        eeprom.destroy().done();
+    }
+
+    #[test]
+    fn test_erase_timeout() {
+        let expectations = [
+            Transaction::write_read(ADDR, vec![0x00, 0x40], vec![0x12]),
+            Transaction::write(ADDR, vec![0x00, 0x40, 0xFF]),
+            busy(),
+            busy(),
+            Transaction::read(ADDR, vec![0]),
+        ];
+        let i2c = Mock::new(&expectations);
+        let eeprom = Eeprom24x::new_24x32(i2c, SlaveAddr::default());
+        let delay = CheckedDelay::new(&[
+            delay::Transaction::delay_us(500),
+            delay::Transaction::delay_us(500),
+        ]);
+        let mut eeprom = Polled::new(eeprom, Timeout::new(delay, 500, 5_000));
+
+        eeprom.erase(0x40).unwrap();
+
+        let (eeprom, poll) = eeprom.into_inner();
+        eeprom.destroy().done();
+        poll.into_inner().done();
+    }
+}
@@ -0,0 +1,306 @@
+//! 25xx series SPI EEPROM support
+//!
+//! This module provides a [`Flash`](crate::storage::Flash) implementation for SPI EEPROM chips
+//! like Microchip 25LC/25AA and ST M95 series. Available with the `eeprom25x` feature.
+//!
+//! The chip is driven directly through an embedded-hal 1.0 [`SpiDevice`], handling write-enable
+//! latching, page boundaries and write-in-progress polling.
+
+use crate::poll::{self, Poll};
+use crate::storage::Flash;
+use embedded_hal::spi::{Operation, SpiDevice};
+
+/// Read data from memory
+const READ: u8 = 0x03;
+/// Write data to memory
+const WRITE: u8 = 0x02;
+/// Set the write enable latch
+const WREN: u8 = 0x06;
+/// Read the status register
+const RDSR: u8 = 0x05;
+
+/// Write-in-progress bit of the status register
+const STATUS_WIP: u8 = 0x01;
+/// Opcode bit used as 9th address bit by chips with 1 address byte (e.g. 25xx040)
+const OPCODE_A8: u8 = 0x08;
+
+/// Errors of the SPI EEPROM backend
+#[derive(Debug)]
+pub enum Error<E> {
+    /// SPI bus error
+    Spi(E),
+    /// The EEPROM didn't complete a write cycle in time
+    Timeout,
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(err: E) -> Self {
+        Self::Spi(err)
+    }
+}
+
+/// SPI EEPROM chip of the 25xx series
+///
+/// # Type Parameters
+///
+/// * `SPI` - The SPI device the chip is connected to
+/// * `PAGE_SIZE` - The write page size of the chip in bytes (e.g. 16 for 25xx040, 32 for
+///   25xx640, 64 for 25xx256, 256 for M95M02)
+/// * `ADDR_BYTES` - The number of address bytes sent with each command (1 for chips up to 4
+///   Kbit, 2 for chips up to 512 Kbit, 3 for larger chips)
+/// * `P` - The strategy to poll for write completion with, see [`poll`]
+#[derive(Debug)]
+pub struct Eeprom25x<SPI, const PAGE_SIZE: u32, const ADDR_BYTES: usize, P> {
+    spi: SPI,
+    poll: P,
+}
+
+impl<SPI: SpiDevice, const PAGE_SIZE: u32, const ADDR_BYTES: usize, P: Poll>
+    Eeprom25x<SPI, PAGE_SIZE, ADDR_BYTES, P>
+{
+    /// Create a new driver for a 25xx series SPI EEPROM
+    ///
+    /// Write cycles take up to 5ms, while the status register is read back in a
+    /// few microseconds at SPI clock rates, so polling should be bounded by time
+    /// with a [`Timeout`](crate::poll::Timeout).
+    pub const fn new(spi: SPI, poll: P) -> Self {
+        // Sanity check
+        const {
+            assert!(
+                PAGE_SIZE.is_power_of_two(),
+                "PAGE_SIZE must be a power of two"
+            );
+            assert!(
+                ADDR_BYTES >= 1 && ADDR_BYTES <= 3,
+                "ADDR_BYTES must be 1, 2 or 3"
+            );
+        }
+
+        Self { spi, poll }
+    }
+
+    /// Consume the driver and return the underlying SPI device and polling strategy
+    pub fn into_inner(self) -> (SPI, P) {
+        (self.spi, self.poll)
+    }
+
+    /// Encode a command with its address, returns the buffer and the used length
+    const fn command(opcode: u8, addr: u32) -> ([u8; 4], usize) {
+        let mut buf = [0u8; 4];
+        buf[0] = opcode;
+        if ADDR_BYTES == 1 && addr > 0xFF {
+            buf[0] |= OPCODE_A8;
+        }
+
+        let addr = addr.to_be_bytes();
+        let mut i = 0;
+        while i < ADDR_BYTES {
+            buf[1 + i] = addr[addr.len() - ADDR_BYTES + i];
+            i += 1;
+        }
+
+        (buf, 1 + ADDR_BYTES)
+    }
+
+    /// Poll the status register until the write cycle has completed
+    fn wait_ready(&mut self) -> Result<(), Error<SPI::Error>> {
+        let spi = &mut self.spi;
+        let ready = poll::until(&mut self.poll, || {
+            let mut status = [0u8; 1];
+            spi.transaction(&mut [Operation::Write(&[RDSR]), Operation::Read(&mut status)])?;
+            Ok::<_, SPI::Error>(status[0] & STATUS_WIP == 0)
+        })?;
+        if ready { Ok(()) } else { Err(Error::Timeout) }
+    }
+
+    /// Write data within a single page
+    fn write_page(&mut self, addr: u32, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        // The write enable latch is reset after every write cycle
+        self.spi.write(&[WREN])?;
+
+        let (cmd, len) = Self::command(WRITE, addr);
+        self.spi
+            .transaction(&mut [Operation::Write(&cmd[..len]), Operation::Write(data)])?;
+
+        self.wait_ready()
+    }
+}
+
+/// Flash trait implementation for 25xx series SPI EEPROM chips
+impl<SPI: SpiDevice, const PAGE_SIZE: u32, const ADDR_BYTES: usize, P: Poll> Flash
+    for Eeprom25x<SPI, PAGE_SIZE, ADDR_BYTES, P>
+{
+    type Error = Error<SPI::Error>;
+
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let (cmd, len) = Self::command(READ, addr);
+        self.spi
+            .transaction(&mut [Operation::Write(&cmd[..len]), Operation::Read(buf)])?;
+        Ok(())
+    }
+
+    fn write(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.write_slice(addr, data)
+    }
+
+    fn write_slice(&mut self, mut addr: u32, mut data: &[u8]) -> Result<(), Self::Error> {
+        // Writes crossing a page boundary would wrap around within the page
+        while !data.is_empty() {
+            let page_remaining = PAGE_SIZE - addr % PAGE_SIZE;
+            let write_size = data.len().min(page_remaining as usize);
+            let (to_write, remaining) = data.split_at(write_size);
+            self.write_page(addr, to_write)?;
+            addr = addr.saturating_add(write_size as u32);
+            data = remaining;
+        }
+        Ok(())
+    }
+
+    fn erase(&mut self, addr: u32) -> Result<(), Self::Error> {
+        let mut byte = [0u8; 1];
+        self.read(addr, &mut byte)?;
+        if byte[0] != Self::ERASED {
+            self.write_page(addr, &[Self::ERASED])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::poll::MaxAttempts;
+    use embedded_hal_mock::eh1::spi::{Mock, Transaction};
+    use std::vec;
+
+    fn write_enable() -> [Transaction<u8>; 3] {
+        [
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![WREN]),
+            Transaction::transaction_end(),
+        ]
+    }
+
+    fn read_status(status: u8) -> [Transaction<u8>; 4] {
+        [
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![RDSR]),
+            Transaction::read_vec(vec![status]),
+            Transaction::transaction_end(),
+        ]
+    }
+
+    #[test]
+    fn test_read() {
+        let expectations = [
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![READ, 0x01, 0x23]),
+            Transaction::read_vec(vec![1, 2, 3]),
+            Transaction::transaction_end(),
+        ];
+        let spi = Mock::new(&expectations);
+        let mut eeprom = Eeprom25x::<_, 32, 2, _>::new(spi, MaxAttempts(3));
+
+        let mut buf = [0u8; 3];
+        eeprom.read(0x123, &mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3]);
+
+        eeprom.into_inner().0.done();
+    }
+
+    #[test]
+    fn test_write_page_boundary() {
+        let mut expectations = vec![];
+        expectations.extend(write_enable());
+        expectations.extend([
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![WRITE, 0x00, 0x1E]),
+            Transaction::write_vec(vec![1, 2]),
+            Transaction::transaction_end(),
+        ]);
+        expectations.extend(read_status(STATUS_WIP));
+        expectations.extend(read_status(0));
+        expectations.extend(write_enable());
+        expectations.extend([
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![WRITE, 0x00, 0x20]),
+            Transaction::write_vec(vec![3, 4, 5]),
+            Transaction::transaction_end(),
+        ]);
+        expectations.extend(read_status(0));
+        let spi = Mock::new(&expectations);
+        let mut eeprom = Eeprom25x::<_, 32, 2, _>::new(spi, MaxAttempts(3));
+
+        let mut data = [1, 2, 3, 4, 5];
+        eeprom.write(0x1E, &mut data).unwrap();
+
+        eeprom.into_inner().0.done();
+    }
+
+    #[test]
+    fn test_erase() {
+        let mut expectations = vec![
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![READ, 0x00, 0x40]),
+            Transaction::read_vec(vec![0x12]),
+            Transaction::transaction_end(),
+        ];
+        expectations.extend(write_enable());
+        expectations.extend([
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![WRITE, 0x00, 0x40]),
+            Transaction::write_vec(vec![0xFF]),
+            Transaction::transaction_end(),
+        ]);
+        expectations.extend(read_status(0));
+        // Already erased, no write needed
+        expectations.extend([
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![READ, 0x00, 0x80]),
+            Transaction::read_vec(vec![0xFF]),
+            Transaction::transaction_end(),
+        ]);
+        let spi = Mock::new(&expectations);
+        let mut eeprom = Eeprom25x::<_, 32, 2, _>::new(spi, MaxAttempts(3));
+
+        eeprom.erase(0x40).unwrap();
+        eeprom.erase(0x80).unwrap();
+
+        eeprom.into_inner().0.done();
+    }
+
+    #[test]
+    fn test_address_bytes() {
+        assert_eq!(
+            Eeprom25x::<Mock<u8>, 16, 1, MaxAttempts>::command(READ, 0x1AB),
+            ([READ | OPCODE_A8, 0xAB, 0, 0], 2)
+        );
+        assert_eq!(
+            Eeprom25x::<Mock<u8>, 256, 3, MaxAttempts>::command(WRITE, 0x3_4567),
+            ([WRITE, 0x03, 0x45, 0x67], 4)
+        );
+    }
+
+    #[test]
+    fn test_write_timeout() {
+        let mut expectations = vec![];
+        expectations.extend(write_enable());
+        expectations.extend([
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![WRITE, 0x00, 0x00]),
+            Transaction::write_vec(vec![1]),
+            Transaction::transaction_end(),
+        ]);
+        expectations.extend(read_status(STATUS_WIP));
+        expectations.extend(read_status(STATUS_WIP));
+        let spi = Mock::new(&expectations);
+        let mut eeprom = Eeprom25x::<_, 32, 2, _>::new(spi, MaxAttempts(2));
+
+        let err = eeprom.write(0, &mut [1]).unwrap_err();
+        assert!(matches!(err, Error::Timeout));
+
+        eeprom.into_inner().0.done();
+    }
+}
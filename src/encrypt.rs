@@ -0,0 +1,165 @@
+//! Savegame encryption
+//!
+//! This module provides the encryption of savegames of a [`Storage`](crate::storage::Storage)
+//! created with [`Storage::with_encryption`](crate::storage::Storage::with_encryption).
+//! Available with the `encrypt` feature.
+//!
+//! Savegames are encrypted with ChaCha20 in a synthetic IV construction: the IV is
+//! HMAC-SHA256 over the previous checksum, the length field (including the flags) and the
+//! plaintext, truncated to
+//! [`IV_SIZE`] bytes, and its first 12 bytes are used as the ChaCha20 nonce. The IV is stored
+//! in front of the ciphertext and also authenticates the savegame, so it's verified by
+//! [`Storage::scan`](crate::storage::Storage::scan) and
+//! [`Storage::read`](crate::storage::Storage::read) like a [`mac`](crate::mac).
+//!
+//! Since the nonce is derived from the plaintext, no counter has to be kept and nonces don't
+//! repeat across appends, even after an interrupted write or erasing the storage. Only
+//! identical savegames following the same previous savegame encrypt to identical ciphertexts.
+//!
+//! The encryption and MAC keys are derived from the [`Key`] given to the storage.
+
+use crate::chksum::Chksum;
+use crate::mac::{Key, Mac};
+use chacha20::ChaCha20;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+
+/// Size of the IV stored in front of each savegame in bytes
+pub const IV_SIZE: usize = crate::mac::TAG_SIZE;
+
+/// Derive a subkey for one purpose from the storage key
+fn derive(key: &Key, label: &[u8]) -> Key {
+    Key::new(Mac::new(key, label).finalize_full())
+}
+
+/// Start the synthetic IV computation for a savegame
+fn siv(key: &Key, prev: Chksum, flags: u8, len: usize) -> Mac {
+    let len = (u32::from(flags) << 24) | len as u32;
+    let mut header = [0u8; Chksum::SIZE + 4];
+    header[..Chksum::SIZE].copy_from_slice(&prev.to_bytes());
+    header[Chksum::SIZE..].copy_from_slice(&len.to_be_bytes());
+    Mac::new(&derive(key, b"embedded-savegame iv"), &header)
+}
+
+/// Create the stream cipher for a savegame
+pub(crate) fn cipher(key: &Key, iv: &[u8; IV_SIZE]) -> ChaCha20 {
+    let key = derive(key, b"embedded-savegame encrypt");
+    let mut nonce = [0u8; 12];
+    nonce.copy_from_slice(&iv[..12]);
+    ChaCha20::new(key.as_bytes().into(), &nonce.into())
+}
+
+/// Compute the IV of savegame data, returns it with the cipher to encrypt the data
+///
+/// The savegame is the concatenation of `parts`. The data is left untouched, so it
+/// can be encrypted in chunks while it's written.
+pub(crate) fn seal(
+    key: &Key,
+    prev: Chksum,
+    flags: u8,
+    parts: &[&[u8]],
+) -> ([u8; IV_SIZE], ChaCha20) {
+    let len = parts.iter().map(|part| part.len()).sum();
+    let mut mac = siv(key, prev, flags, len);
+    for part in parts {
+        mac.update(part);
+    }
+    let iv = mac.finalize();
+    (iv, cipher(key, &iv))
+}
+
+/// Incremental decryption and verification of a savegame
+pub(crate) struct Opener {
+    cipher: ChaCha20,
+    mac: Mac,
+    iv: [u8; IV_SIZE],
+}
+
+impl Opener {
+    /// Start decrypting a savegame of `len` bytes with the stored `iv`
+    pub(crate) fn new(key: &Key, prev: Chksum, flags: u8, len: usize, iv: [u8; IV_SIZE]) -> Self {
+        Self {
+            cipher: cipher(key, &iv),
+            mac: siv(key, prev, flags, len),
+            iv,
+        }
+    }
+
+    /// Decrypt the next chunk of the savegame in place
+    pub(crate) fn decrypt(&mut self, data: &mut [u8]) {
+        self.cipher.apply_keystream(data);
+        self.mac.update(data);
+    }
+
+    /// Check the decrypted data against the IV in constant time
+    pub(crate) fn verify(self) -> bool {
+        self.mac.verify(&self.iv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: Key = Key::new([0x42; 32]);
+
+    fn seal_in_place(key: &Key, prev: Chksum, flags: u8, data: &mut [u8]) -> [u8; IV_SIZE] {
+        let (iv, mut cipher) = seal(key, prev, flags, &[data]);
+        cipher.apply_keystream(data);
+        iv
+    }
+
+    #[test]
+    fn test_seal_open() {
+        let mut data = *b"hunter2";
+        let iv = seal_in_place(&KEY, Chksum::zero(), 0, &mut data);
+        assert_ne!(&data, b"hunter2");
+
+        // Decrypting in chunks yields the plaintext
+        let mut opener = Opener::new(&KEY, Chksum::zero(), 0, data.len(), iv);
+        let (head, tail) = data.split_at_mut(3);
+        opener.decrypt(head);
+        opener.decrypt(tail);
+        assert!(opener.verify());
+        assert_eq!(&data, b"hunter2");
+    }
+
+    #[test]
+    fn test_nonce_unique() {
+        let mut first = *b"hunter2";
+        let first_iv = seal_in_place(&KEY, Chksum::zero(), 0, &mut first);
+        let mut second = *b"hunter3";
+        let second_iv = seal_in_place(&KEY, Chksum::zero(), 0, &mut second);
+        assert_ne!(first_iv[..12], second_iv[..12]);
+
+        // The same data after another savegame gets another nonce as well
+        let mut third = *b"hunter2";
+        let third_iv = seal_in_place(&KEY, Chksum::hash(Chksum::zero(), b"x"), 0, &mut third);
+        assert_ne!(first_iv[..12], third_iv[..12]);
+        assert_ne!(first, third);
+    }
+
+    #[test]
+    fn test_open_tampered() {
+        let mut data = *b"hunter2";
+        let iv = seal_in_place(&KEY, Chksum::zero(), 0, &mut data);
+        data[0] ^= 1;
+
+        let mut opener = Opener::new(&KEY, Chksum::zero(), 0, data.len(), iv);
+        opener.decrypt(&mut data);
+        assert!(!opener.verify());
+
+        // The flags are authenticated as well
+        let mut data = *b"hunter2";
+        let iv = seal_in_place(&KEY, Chksum::zero(), 0, &mut data);
+        let mut opener = Opener::new(&KEY, Chksum::zero(), 1, data.len(), iv);
+        opener.decrypt(&mut data);
+        assert!(!opener.verify());
+
+        // Another key can't decrypt
+        let mut data = *b"hunter2";
+        let iv = seal_in_place(&KEY, Chksum::zero(), 0, &mut data);
+        let mut opener = Opener::new(&Key::new([0x43; 32]), Chksum::zero(), 0, data.len(), iv);
+        opener.decrypt(&mut data);
+        assert!(!opener.verify());
+    }
+}
@@ -0,0 +1,241 @@
+//! FRAM support
+//!
+//! This module provides [`Flash`](crate::storage::Flash) implementations for ferroelectric RAM
+//! chips. Available with the `fram` feature.
+//!
+//! - [`I2cFram`]: I2C FRAM like Fujitsu MB85RC or Cypress/Infineon FM24
+//! - [`SpiFram`]: SPI FRAM like Fujitsu MB85RS or Cypress/Infineon FM25
+//!
+//! FRAM is byte-writable, needs no erase cycle and has no write delay, so both implementations
+//! declare [`Flash::NEEDS_ERASE`] as `false`.
+
+use crate::storage::Flash;
+use embedded_hal::i2c::{self, I2c};
+use embedded_hal::spi::{self, SpiDevice};
+
+/// Read data from memory
+const READ: u8 = 0x03;
+/// Write data to memory
+const WRITE: u8 = 0x02;
+/// Set the write enable latch
+const WREN: u8 = 0x06;
+
+/// I2C FRAM chip with 2-byte memory addresses
+///
+/// Address bits above the 16th (e.g. on MB85RC1M) are sent as the lowest bits of the device
+/// address, as specified by these chips.
+#[derive(Debug)]
+pub struct I2cFram<I2C> {
+    i2c: I2C,
+    address: u8,
+}
+
+impl<I2C: I2c> I2cFram<I2C> {
+    /// Default device address with A2, A1 and A0 tied low
+    pub const DEFAULT_ADDRESS: u8 = 0x50;
+
+    /// Create a new driver for an I2C FRAM at the given 7-bit device address
+    pub const fn new(i2c: I2C, address: u8) -> Self {
+        Self { i2c, address }
+    }
+
+    /// Consume the driver and return the underlying I2C bus
+    pub fn into_inner(self) -> I2C {
+        self.i2c
+    }
+
+    /// Split an address into device address and memory address bytes
+    const fn addr(&self, addr: u32) -> (u8, [u8; 2]) {
+        let device = self.address | (addr >> 16) as u8;
+        let [_, _, hi, lo] = addr.to_be_bytes();
+        (device, [hi, lo])
+    }
+}
+
+/// Flash trait implementation for I2C FRAM chips
+impl<I2C: I2c> Flash for I2cFram<I2C> {
+    type Error = I2C::Error;
+
+    const NEEDS_ERASE: bool = false;
+
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let (device, addr) = self.addr(addr);
+        self.i2c.write_read(device, &addr, buf)
+    }
+
+    fn write(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.write_slice(addr, data)
+    }
+
+    fn write_slice(&mut self, addr: u32, data: &[u8]) -> Result<(), Self::Error> {
+        let (device, addr) = self.addr(addr);
+        self.i2c.transaction(
+            device,
+            &mut [i2c::Operation::Write(&addr), i2c::Operation::Write(data)],
+        )
+    }
+
+    fn erase(&mut self, addr: u32) -> Result<(), Self::Error> {
+        self.write(addr, &mut [Self::ERASED])
+    }
+}
+
+/// SPI FRAM chip
+///
+/// # Type Parameters
+///
+/// * `SPI` - The SPI device the chip is connected to
+/// * `ADDR_BYTES` - The number of address bytes sent with each command (2 for chips up to 512
+///   Kbit, 3 for larger chips)
+#[derive(Debug)]
+pub struct SpiFram<SPI, const ADDR_BYTES: usize> {
+    spi: SPI,
+}
+
+impl<SPI: SpiDevice, const ADDR_BYTES: usize> SpiFram<SPI, ADDR_BYTES> {
+    /// Create a new driver for an SPI FRAM
+    pub const fn new(spi: SPI) -> Self {
+        // Sanity check
+        const {
+            assert!(
+                ADDR_BYTES >= 2 && ADDR_BYTES <= 3,
+                "ADDR_BYTES must be 2 or 3"
+            );
+        }
+
+        Self { spi }
+    }
+
+    /// Consume the driver and return the underlying SPI device
+    pub fn into_inner(self) -> SPI {
+        self.spi
+    }
+
+    /// Encode a command with its address, returns the buffer and the used length
+    const fn command(opcode: u8, addr: u32) -> ([u8; 4], usize) {
+        let [_, a2, a1, a0] = addr.to_be_bytes();
+        if ADDR_BYTES == 2 {
+            ([opcode, a1, a0, 0], 3)
+        } else {
+            ([opcode, a2, a1, a0], 4)
+        }
+    }
+}
+
+/// Flash trait implementation for SPI FRAM chips
+impl<SPI: SpiDevice, const ADDR_BYTES: usize> Flash for SpiFram<SPI, ADDR_BYTES> {
+    type Error = SPI::Error;
+
+    const NEEDS_ERASE: bool = false;
+
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let (cmd, len) = Self::command(READ, addr);
+        self.spi.transaction(&mut [
+            spi::Operation::Write(&cmd[..len]),
+            spi::Operation::Read(buf),
+        ])
+    }
+
+    fn write(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.write_slice(addr, data)
+    }
+
+    fn write_slice(&mut self, addr: u32, data: &[u8]) -> Result<(), Self::Error> {
+        // The write enable latch is reset after every write
+        self.spi.write(&[WREN])?;
+
+        let (cmd, len) = Self::command(WRITE, addr);
+        self.spi.transaction(&mut [
+            spi::Operation::Write(&cmd[..len]),
+            spi::Operation::Write(data),
+        ])
+    }
+
+    fn erase(&mut self, addr: u32) -> Result<(), Self::Error> {
+        self.write(addr, &mut [Self::ERASED])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use embedded_hal_mock::eh1::{i2c as i2c_mock, spi as spi_mock};
+    use std::vec;
+
+    #[test]
+    fn test_i2c_read_write() {
+        let expectations = [
+            i2c_mock::Transaction::write_read(0x50, vec![0x12, 0x34], vec![1, 2, 3]),
+            i2c_mock::Transaction::transaction_start(0x51),
+            i2c_mock::Transaction::write(0x51, vec![0x00, 0x10]),
+            i2c_mock::Transaction::write(0x51, vec![4, 5]),
+            i2c_mock::Transaction::transaction_end(0x51),
+        ];
+        let i2c = i2c_mock::Mock::new(&expectations);
+        let mut fram = I2cFram::new(i2c, I2cFram::<i2c_mock::Mock>::DEFAULT_ADDRESS);
+
+        let mut buf = [0u8; 3];
+        fram.read(0x1234, &mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3]);
+
+        let mut data = [4, 5];
+        fram.write(0x1_0010, &mut data).unwrap();
+
+        fram.into_inner().done();
+    }
+
+    #[test]
+    fn test_i2c_erase() {
+        let expectations = [
+            i2c_mock::Transaction::transaction_start(0x50),
+            i2c_mock::Transaction::write(0x50, vec![0x00, 0x40]),
+            i2c_mock::Transaction::write(0x50, vec![0xFF]),
+            i2c_mock::Transaction::transaction_end(0x50),
+        ];
+        let i2c = i2c_mock::Mock::new(&expectations);
+        let mut fram = I2cFram::new(i2c, 0x50);
+
+        fram.erase(0x40).unwrap();
+
+        fram.into_inner().done();
+    }
+
+    #[test]
+    fn test_spi_read_write() {
+        let expectations = [
+            spi_mock::Transaction::transaction_start(),
+            spi_mock::Transaction::write_vec(vec![READ, 0x01, 0x23, 0x45]),
+            spi_mock::Transaction::read_vec(vec![1, 2, 3]),
+            spi_mock::Transaction::transaction_end(),
+            spi_mock::Transaction::transaction_start(),
+            spi_mock::Transaction::write_vec(vec![WREN]),
+            spi_mock::Transaction::transaction_end(),
+            spi_mock::Transaction::transaction_start(),
+            spi_mock::Transaction::write_vec(vec![WRITE, 0x00, 0x00, 0x7F]),
+            // No page boundaries on FRAM
+            spi_mock::Transaction::write_vec(vec![0xAA; 300]),
+            spi_mock::Transaction::transaction_end(),
+        ];
+        let spi = spi_mock::Mock::new(&expectations);
+        let mut fram = SpiFram::<_, 3>::new(spi);
+
+        let mut buf = [0u8; 3];
+        fram.read(0x1_2345, &mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3]);
+
+        let mut data = [0xAA; 300];
+        fram.write(0x7F, &mut data).unwrap();
+
+        fram.into_inner().done();
+    }
+
+    #[test]
+    fn test_spi_address_bytes() {
+        assert_eq!(
+            SpiFram::<spi_mock::Mock<u8>, 2>::command(READ, 0x1234),
+            ([READ, 0x12, 0x34, 0], 3)
+        );
+    }
+}
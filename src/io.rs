@@ -0,0 +1,550 @@
+//! Streaming access to savegames
+//!
+//! This module provides [`SaveReader`] and [`SaveWriter`], an [`embedded_io`] reader and
+//! writer for savegames, created with [`Storage::reader`] and [`Storage::writer`]. Available
+//! with the `io` feature.
+//!
+//! Unlike [`Storage::read`] and [`Storage::write`], savegames don't have to fit into a single
+//! buffer. The reader and writer walk the slots of a savegame, skipping the header and the
+//! continuation bytes, so savegames can be serialized and deserialized incrementally.
+//!
+//! Savegames of a storage with a key (`mac` and `encrypt` features) are verified before the
+//! reader is handed out, encrypted savegames are decrypted while reading. Compressed and delta
+//! savegames can't be streamed. The writer isn't available with a key, as the MAC or IV covers
+//! the header, which is only known once the savegame is complete.
+
+use crate::Slot;
+use crate::chksum::{Chksum, Hasher};
+#[cfg(feature = "encrypt")]
+use crate::encrypt;
+use crate::storage::{Cursor, Flash, Storage};
+#[cfg(feature = "encrypt")]
+use chacha20::{
+    ChaCha20,
+    cipher::{StreamCipher, StreamCipherSeek},
+};
+use embedded_io::{ErrorKind, ErrorType, Read, Seek, SeekFrom, Write};
+
+/// Errors reported by [`SaveReader`] and [`SaveWriter`]
+#[derive(Debug, PartialEq)]
+pub enum Error<E> {
+    /// The flash reported an error
+    Flash(E),
+    /// Seeking to a negative or overflowing position
+    InvalidSeek,
+    /// The savegame doesn't fit into the slots
+    Full,
+}
+
+impl<E: core::fmt::Debug> embedded_io::Error for Error<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Flash(_) => ErrorKind::Other,
+            Self::InvalidSeek => ErrorKind::InvalidInput,
+            Self::Full => ErrorKind::OutOfMemory,
+        }
+    }
+}
+
+/// Reader for a savegame spanning one or more slots
+///
+/// Implements [`Read`] and [`Seek`]. Reads past the end of the savegame return `0` bytes.
+pub struct SaveReader<'a, F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> {
+    storage: &'a mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    idx: usize,
+    /// Offset of the savegame data behind the IV
+    start: usize,
+    len: usize,
+    pos: usize,
+    cursor: Cursor,
+    #[cfg(feature = "encrypt")]
+    cipher: Option<ChaCha20>,
+}
+
+/// Writer for a new savegame at the next free slot
+///
+/// Implements [`Write`]. The checksum is computed while the data is written, and
+/// the header is only written by [`SaveWriter::finish`]. Dropping the writer
+/// without finishing it leaves the previous savegame as the most recent one, the
+/// slots used by the unfinished savegame are skipped by the next append.
+pub struct SaveWriter<'a, F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> {
+    storage: &'a mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    idx: usize,
+    prev: Chksum,
+    hasher: Hasher,
+    len: usize,
+    /// The largest savegame that fits without overwriting the latest one
+    capacity: usize,
+    cursor: Cursor,
+    finished: bool,
+}
+
+impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> Storage<F, SLOT_SIZE, SLOT_COUNT> {
+    /// Open a savegame for streaming from a specific slot index
+    ///
+    /// The slot index must point to the first slot of the savegame, like for
+    /// [`Storage::read`]. Returns `Ok(None)` for compressed or delta savegames,
+    /// and if the savegame fails verification with [`Storage::with_key`].
+    pub fn reader(
+        &mut self,
+        idx: usize,
+    ) -> Result<Option<SaveReader<'_, F, SLOT_SIZE, SLOT_COUNT>>, F::Error> {
+        let idx = idx % SLOT_COUNT;
+        let slot = self.read_header(idx)?;
+        let Some(len) = (slot.len as usize).checked_sub(self.overhead()) else {
+            return Ok(None);
+        };
+        if slot.encoding() != 0 || !self.authenticate(&slot)? {
+            return Ok(None);
+        }
+
+        #[cfg(feature = "encrypt")]
+        let (start, cipher) = match self.encrypt_key().cloned() {
+            Some(key) => {
+                let mut iv = [0u8; encrypt::IV_SIZE];
+                self.read_data(&mut self.cursor(idx), &mut iv)?;
+                (iv.len(), Some(encrypt::cipher(&key, &iv)))
+            }
+            None => (0, None),
+        };
+        #[cfg(not(feature = "encrypt"))]
+        let start = 0;
+
+        Ok(Some(SaveReader {
+            cursor: self.cursor_at(idx, start),
+            storage: self,
+            idx,
+            start,
+            len,
+            pos: 0,
+            #[cfg(feature = "encrypt")]
+            cipher,
+        }))
+    }
+
+    /// Start streaming a new savegame into the next free slot
+    ///
+    /// Like [`Storage::append`], but the data is written incrementally with
+    /// the returned [`SaveWriter`]. The first slot is erased right away.
+    /// Writes fail with [`Error::Full`] once the next byte would overwrite
+    /// the latest savegame. Returns `Ok(None)` for a storage with a key.
+    pub fn writer(&mut self) -> Result<Option<SaveWriter<'_, F, SLOT_SIZE, SLOT_COUNT>>, F::Error> {
+        if self.overhead() > 0 {
+            return Ok(None);
+        }
+
+        let capacity = self.capacity().min(Slot::MAX_LEN);
+        let (cursor, prev) = self.begin_append()?;
+        Ok(Some(SaveWriter {
+            idx: cursor.idx,
+            storage: self,
+            prev,
+            hasher: Hasher::new(prev),
+            len: 0,
+            capacity,
+            cursor,
+            finished: false,
+        }))
+    }
+}
+
+impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize>
+    SaveReader<'_, F, SLOT_SIZE, SLOT_COUNT>
+{
+    /// Length of the savegame in bytes
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the savegame is empty
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize>
+    SaveWriter<'_, F, SLOT_SIZE, SLOT_COUNT>
+{
+    /// Number of bytes written so far
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if no data was written yet
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Finish the savegame by writing its header
+    ///
+    /// Once the header is written, the scanner finds the new savegame as the
+    /// most recent one.
+    pub fn finish(mut self) -> Result<(), F::Error> {
+        self.storage.flush(&mut self.cursor)?;
+        let slot = Slot {
+            idx: self.idx,
+            chksum: self.hasher.finish(),
+            len: self.len as u32,
+            prev: self.prev,
+            flags: self.storage.lap_flag(self.idx),
+        };
+        self.storage.write_header(&slot)?;
+
+        let next = self.cursor.idx.saturating_add(1) % SLOT_COUNT;
+        self.storage.appended(next, slot.chksum);
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> Drop
+    for SaveWriter<'_, F, SLOT_SIZE, SLOT_COUNT>
+{
+    fn drop(&mut self) {
+        if !self.finished && !self.is_empty() {
+            // Drop can't report errors, the slots are skipped regardless
+            let _ = self.storage.skip(&self.cursor);
+        }
+    }
+}
+
+impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> ErrorType
+    for SaveWriter<'_, F, SLOT_SIZE, SLOT_COUNT>
+{
+    type Error = Error<F::Error>;
+}
+
+impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> Write
+    for SaveWriter<'_, F, SLOT_SIZE, SLOT_COUNT>
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let len = buf.len().min(self.capacity.saturating_sub(self.len));
+        if len == 0 {
+            return Err(Error::Full);
+        }
+
+        let chunk = &buf[..len];
+        self.hasher.update(chunk);
+        self.storage
+            .write_data(&mut self.cursor, chunk)
+            .map_err(Error::Flash)?;
+        self.len += len;
+        Ok(len)
+    }
+
+    /// Data is only committed by [`SaveWriter::finish`], so this does nothing
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> ErrorType
+    for SaveReader<'_, F, SLOT_SIZE, SLOT_COUNT>
+{
+    type Error = Error<F::Error>;
+}
+
+impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> Read
+    for SaveReader<'_, F, SLOT_SIZE, SLOT_COUNT>
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let len = buf.len().min(self.len.saturating_sub(self.pos));
+        let buf = &mut buf[..len];
+        self.storage
+            .read_data(&mut self.cursor, buf)
+            .map_err(Error::Flash)?;
+        #[cfg(feature = "encrypt")]
+        if let Some(cipher) = &mut self.cipher {
+            cipher.apply_keystream(buf);
+        }
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> Seek
+    for SaveReader<'_, F, SLOT_SIZE, SLOT_COUNT>
+{
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => (self.len as u64).checked_add_signed(offset),
+            SeekFrom::Current(offset) => (self.pos as u64).checked_add_signed(offset),
+        };
+        let pos = pos
+            .and_then(|pos| usize::try_from(pos).ok())
+            .ok_or(Error::InvalidSeek)?;
+
+        self.pos = pos;
+        let offset = self.start.saturating_add(pos.min(self.len));
+        self.cursor = self.storage.cursor_at(self.idx, offset);
+        #[cfg(feature = "encrypt")]
+        if let Some(cipher) = &mut self.cipher {
+            cipher.seek(pos.min(self.len) as u64);
+        }
+        Ok(pos as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockFlash, SectorMockFlash};
+
+    const SLOT_SIZE: usize = 64;
+    const SLOT_COUNT: usize = 8;
+    const SIZE: usize = SLOT_SIZE * SLOT_COUNT;
+
+    fn savegame() -> [u8; SLOT_SIZE * 3] {
+        core::array::from_fn(|i| i as u8)
+    }
+
+    fn test_reader<F: Flash<Error = core::convert::Infallible>>(
+        mut storage: Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        let data = *b"first";
+        let Ok(()) = storage.append(&data);
+        let data = savegame();
+        let Ok(()) = storage.append(&data);
+
+        let Ok(Some(mut reader)) = storage.reader(1) else {
+            panic!("no savegame found");
+        };
+        assert_eq!(reader.len(), data.len());
+
+        // Read in chunks not aligned to the slots
+        let mut buf = [0u8; SLOT_SIZE * 3];
+        for chunk in buf.chunks_mut(7) {
+            assert_eq!(reader.read(chunk), Ok(chunk.len()));
+        }
+        assert_eq!(buf, data);
+        assert_eq!(reader.read(&mut buf), Ok(0));
+
+        // Seek into the continuation slots
+        let mut buf = [0u8; 5];
+        for pos in [0, 51, 52, 53, 114, 115, 116, 187] {
+            assert_eq!(reader.seek(SeekFrom::Start(pos)), Ok(pos));
+            assert_eq!(reader.read_exact(&mut buf), Ok(()));
+            assert_eq!(buf, data[pos as usize..][..5]);
+        }
+        assert_eq!(reader.seek(SeekFrom::End(-1)), Ok(data.len() as u64 - 1));
+        assert_eq!(reader.read(&mut buf), Ok(1));
+        assert_eq!(buf[0], data[data.len() - 1]);
+        assert_eq!(
+            reader.seek(SeekFrom::Current(-200)),
+            Err(Error::InvalidSeek)
+        );
+
+        let Ok(Some(mut reader)) = storage.reader(0) else {
+            panic!("no savegame found");
+        };
+        let mut buf = [0u8; 8];
+        assert_eq!(reader.read(&mut buf), Ok(5));
+        assert_eq!(&buf[..5], b"first");
+    }
+
+    #[test]
+    fn test_at24cxx_reader() {
+        let flash = MockFlash::<SIZE>::new();
+        test_reader(Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash));
+    }
+
+    #[test]
+    fn test_w25qxx_reader() {
+        let flash = SectorMockFlash::<SLOT_SIZE, SLOT_COUNT>::new();
+        test_reader(Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash));
+    }
+
+    fn test_writer<F: Flash<Error = core::convert::Infallible>>(
+        mut storage: Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        let data = *b"first";
+        let Ok(()) = storage.append(&data);
+
+        let data = savegame();
+        let Ok(Some(mut writer)) = storage.writer() else {
+            panic!("no writer");
+        };
+        for chunk in data.chunks(7) {
+            assert_eq!(writer.write_all(chunk), Ok(()));
+        }
+        assert_eq!(writer.len(), data.len());
+        let Ok(()) = writer.finish();
+
+        let Ok(Some(slot)) = storage.scan() else {
+            panic!("no savegame found");
+        };
+        assert_eq!(slot.idx, 1);
+        let first = Chksum::hash(Chksum::zero(), b"first");
+        assert_eq!(slot.chksum, Chksum::hash(first, &data));
+        let mut buf = [0u8; SLOT_SIZE * 3];
+        let Ok(slice) = storage.read(1, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&data[..]));
+
+        // An unfinished savegame isn't found, the next savegame skips its slot
+        let Some(hint) = storage.hint() else {
+            panic!("no hint");
+        };
+        let Ok(Some(mut writer)) = storage.writer() else {
+            panic!("no writer");
+        };
+        assert_eq!(writer.write_all(b"unfinished"), Ok(()));
+        drop(writer);
+        let data = *b"second";
+        let Ok(()) = storage.append(&data);
+        let Ok(Some(slot)) = storage.scan() else {
+            panic!("no savegame found");
+        };
+        assert_eq!(slot.idx, 6);
+        let Ok(slice) = storage.read(6, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&b"second"[..]));
+        let first = Chksum::hash(Chksum::zero(), b"first");
+        assert_eq!(slot.prev, Chksum::hash(first, &savegame()));
+
+        // A hint from before the unfinished savegame is stale
+        storage.reset();
+        let Ok(Some(slot)) = storage.scan_with_hint(hint) else {
+            panic!("no savegame found");
+        };
+        assert_eq!(slot.idx, 6);
+        storage.reset();
+        let Ok(Some(slot)) = storage.scan_fast() else {
+            panic!("no savegame found");
+        };
+        assert_eq!(slot.idx, 6);
+    }
+
+    #[test]
+    fn test_at24cxx_writer() {
+        let flash = MockFlash::<SIZE>::new();
+        test_writer(Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash));
+    }
+
+    #[test]
+    fn test_w25qxx_writer() {
+        let flash = SectorMockFlash::<SLOT_SIZE, SLOT_COUNT>::new();
+        test_writer(Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash));
+    }
+
+    #[test]
+    fn test_packed_writer() {
+        let flash = SectorMockFlash::<{ SLOT_SIZE * 4 }, { SLOT_COUNT / 4 }>::new();
+        test_writer(Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash));
+    }
+
+    #[test]
+    fn test_writer_full() {
+        let flash = MockFlash::<SIZE>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash);
+        let Ok(Some(mut writer)) = storage.writer() else {
+            panic!("no writer");
+        };
+        assert_eq!(writer.write_all(&[0x42; SIZE]), Err(Error::Full));
+        // The savegame fills all slots, without wrapping onto its header
+        assert_eq!(writer.len(), SIZE - Slot::HEADER_SIZE - (SLOT_COUNT - 1));
+        let Ok(()) = writer.finish();
+
+        let Ok(Some(mut reader)) = storage.reader(0) else {
+            panic!("no savegame found");
+        };
+        let mut buf = [0u8; SIZE];
+        assert_eq!(reader.read(&mut buf[..SLOT_SIZE]), Ok(SLOT_SIZE));
+        assert_eq!(buf[..SLOT_SIZE], [0x42; SLOT_SIZE]);
+    }
+
+    fn test_writer_full_keeps_latest<F: Flash<Error = core::convert::Infallible>>(
+        mut storage: Storage<F, SLOT_SIZE, SLOT_COUNT>,
+        capacity: usize,
+    ) {
+        let Ok(()) = storage.append(b"first");
+        let Ok(()) = storage.append(b"second");
+
+        // The savegame stops in front of the latest one instead of wrapping
+        let Ok(Some(mut writer)) = storage.writer() else {
+            panic!("no writer");
+        };
+        assert_eq!(writer.write_all(&[0x42; SIZE]), Err(Error::Full));
+        assert_eq!(writer.len(), capacity);
+        drop(writer);
+
+        let Ok(Some(slot)) = storage.scan() else {
+            panic!("no savegame found");
+        };
+        assert_eq!(slot.idx, 1);
+        let mut buf = [0u8; SLOT_SIZE];
+        let Ok(slice) = storage.read(1, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&b"second"[..]));
+    }
+
+    #[test]
+    fn test_at24cxx_writer_full_keeps_latest() {
+        let flash = MockFlash::<SIZE>::new();
+        let storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash);
+        // Slots 2 to 7 and 0
+        test_writer_full_keeps_latest(storage, SLOT_SIZE - Slot::HEADER_SIZE + 6 * (SLOT_SIZE - 1));
+    }
+
+    #[test]
+    fn test_packed_writer_full_keeps_latest() {
+        let flash = SectorMockFlash::<{ SLOT_SIZE * 4 }, { SLOT_COUNT / 4 }>::new();
+        let storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash);
+        // Slots 2 to 7, slot 0 would erase the sector of the latest savegame
+        test_writer_full_keeps_latest(storage, SLOT_SIZE - Slot::HEADER_SIZE + 5 * (SLOT_SIZE - 1));
+    }
+
+    #[cfg(feature = "mac")]
+    #[test]
+    fn test_mac_writer() {
+        let flash = MockFlash::<SIZE>::new();
+        let key = crate::mac::Key::new([0x42; 32]);
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(flash, key);
+        let Ok(writer) = storage.writer();
+        assert!(writer.is_none());
+    }
+
+    #[cfg(feature = "mac")]
+    #[test]
+    fn test_mac_reader_tampered() {
+        let flash = MockFlash::<SIZE>::new();
+        let key = crate::mac::Key::new([0x42; 32]);
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(flash, key);
+        let data = savegame();
+        let Ok(()) = storage.append(&data);
+        let Ok(Some(_)) = storage.reader(0) else {
+            panic!("no savegame found");
+        };
+
+        // Modify a byte in the continuation slot
+        let mut flash = storage.into_inner();
+        let Ok(()) = flash.write(SLOT_SIZE as u32 + 10, &mut [0x00]);
+        let key = crate::mac::Key::new([0x42; 32]);
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(flash, key);
+        let Ok(reader) = storage.reader(0);
+        assert!(reader.is_none());
+    }
+
+    #[cfg(feature = "encrypt")]
+    #[test]
+    fn test_encrypted_reader() {
+        let flash = MockFlash::<SIZE>::new();
+        let key = crate::mac::Key::new([0x42; 32]);
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_encryption(flash, key);
+        let data = savegame();
+        let Ok(()) = storage.append(&data);
+
+        let Ok(Some(mut reader)) = storage.reader(0) else {
+            panic!("no savegame found");
+        };
+        let mut buf = [0u8; SLOT_SIZE * 3];
+        assert_eq!(reader.read_exact(&mut buf), Ok(()));
+        assert_eq!(buf, data);
+
+        let mut buf = [0u8; 5];
+        assert_eq!(reader.seek(SeekFrom::Start(100)), Ok(100));
+        assert_eq!(reader.read_exact(&mut buf), Ok(()));
+        assert_eq!(buf, data[100..105]);
+    }
+}
@@ -8,9 +8,26 @@
 //! # Flash Support
 //!
 //! - `eeprom24x` feature: Support for AT24Cxx EEPROM chips
+//! - `eeprom25x` feature: Support for 25xx series SPI EEPROM chips
+//! - `fram` feature: Support for I2C and SPI FRAM chips
 //! - `w25q` feature: Support for W25Q NOR flash chips
+//! - `spi-nor` feature: Support for SPI NOR flash chips (like W25Q) using embedded-hal 1.0
 //! - `mock` feature: Mock flash implementations for testing
 //!
+//! # Authentication
+//!
+//! - `mac` feature: Authenticate savegames with a keyed MAC to detect tampering
+//! - `encrypt` feature: Encrypt and authenticate savegames
+//!
+//! # Compression
+//!
+//! - `compress` feature: Compress savegames with LZ4 to use fewer slots
+//! - `delta` feature: Store only the changes to the previous savegame, with periodic checkpoints
+//!
+//! # Streaming
+//!
+//! - `io` feature: Read and write savegames incrementally with `embedded-io`
+//!
 //! # Example
 //!
 #![cfg_attr(feature = "mock", doc = r#"```"#)]
@@ -35,8 +52,7 @@
 //! }
 //!
 //! // Write new savegame
-//! let mut save_data = b"game state data".to_vec();
-//! storage.append(&mut save_data)?;
+//! storage.append(b"game state data")?;
 //! # Ok(())
 //! # }
 //! ```
@@ -50,11 +66,32 @@
 //!
 //! The scanner finds the most recent valid savegame by following the checksum chain.
 
+#[cfg(test)]
+extern crate std;
+
 pub mod chksum;
+#[cfg(feature = "compress")]
+pub mod compress;
+#[cfg(feature = "delta")]
+pub mod delta;
 #[cfg(feature = "eeprom24x")]
 pub mod eeprom24x;
+#[cfg(feature = "eeprom25x")]
+pub mod eeprom25x;
+#[cfg(feature = "encrypt")]
+pub mod encrypt;
+#[cfg(feature = "fram")]
+pub mod fram;
+#[cfg(feature = "io")]
+pub mod io;
+#[cfg(feature = "mac")]
+pub mod mac;
 #[cfg(any(test, feature = "mock"))]
 pub mod mock;
+#[cfg(any(feature = "eeprom24x", feature = "eeprom25x", feature = "spi-nor"))]
+pub mod poll;
+#[cfg(feature = "spi-nor")]
+pub mod spi_nor;
 pub mod storage;
 #[cfg(feature = "w25q")]
 pub mod w25q;
@@ -63,6 +100,9 @@ use crate::chksum::Chksum;
 
 const LENGTH_SIZE: usize = 4;
 
+/// Mask of the length bits in the length field, the top byte holds the flags
+const LENGTH_MASK: u32 = 0x00FF_FFFF;
+
 /// A savegame slot containing metadata about stored data
 ///
 /// Each slot represents a savegame header stored in flash memory. Slots form a chain
@@ -75,12 +115,15 @@ const LENGTH_SIZE: usize = 4;
 /// - `chksum`: Checksum of the savegame data
 /// - `len`: Length of the savegame data in bytes
 /// - `prev`: Checksum of the previous savegame (for chain verification)
+///
+/// The flags stored with the length are available through [`Slot::flags`].
 #[derive(Debug, PartialEq)]
 pub struct Slot {
     pub idx: usize,
     pub chksum: Chksum,
     pub len: u32,
     pub prev: Chksum,
+    flags: u8,
 }
 
 impl Slot {
@@ -88,6 +131,44 @@ impl Slot {
     /// The first byte of the checksum is also used to indicate if the slot is in use.
     pub const HEADER_SIZE: usize = Chksum::SIZE * 2 + LENGTH_SIZE;
 
+    /// The largest length of savegame data the header can hold
+    pub const MAX_LEN: usize = LENGTH_MASK as usize;
+
+    /// Flag for savegame data stored compressed with the `compress` feature
+    pub const FLAG_COMPRESSED: u8 = 0x01;
+
+    /// Flag for savegame data stored as a patch against the previous savegame
+    /// with the `delta` feature
+    pub const FLAG_DELTA: u8 = 0x02;
+
+    /// Flag for savegames written in an odd pass over the slots
+    ///
+    /// Continuation slots carry the same bit in their marker, so the slots
+    /// written since the last wrap-around can be told apart from older ones,
+    /// see [`Storage::scan_fast`](crate::storage::Storage::scan_fast).
+    pub const FLAG_LAP: u8 = 0x80;
+
+    /// Marker at the start of each continuation slot of a savegame
+    ///
+    /// Savegames spanning several slots start every following slot with this
+    /// byte instead of a header, with [`Slot::FLAG_LAP`] in the lowest bit.
+    /// It has the most significant bit set, which the checksum at the start
+    /// of a header never has, so the payload behind the marker is never
+    /// mistaken for a header. It's stored encoded like the header.
+    pub const CONTINUATION_MARKER: u8 = 0xC0;
+
+    /// How the savegame data is encoded, see [`Slot::FLAG_COMPRESSED`] and
+    /// [`Slot::FLAG_DELTA`], and the pass it was written in, see
+    /// [`Slot::FLAG_LAP`]
+    pub const fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// The flags describing how the savegame data is encoded
+    pub(crate) const fn encoding(&self) -> u8 {
+        self.flags & !Self::FLAG_LAP
+    }
+
     /// Create a new slot for the given data
     ///
     /// Calculates the checksum for the data and creates a slot that references
@@ -106,6 +187,7 @@ impl Slot {
             chksum,
             len,
             prev,
+            flags: 0,
         }
     }
 
@@ -134,9 +216,17 @@ impl Slot {
     ///
     /// * `SLOT_SIZE` - The size of each slot in bytes
     pub fn used_bytes<const SLOT_SIZE: usize>(&self) -> usize {
-        let mut size = Self::HEADER_SIZE;
+        self.padded_bytes(SLOT_SIZE, 1)
+    }
+
+    /// Calculate the total number of bytes used by this savegame with padding
+    ///
+    /// Like [`Slot::used_bytes`], but the header and the continuation byte are
+    /// padded to the programming unit `write_size` of the flash.
+    pub(crate) fn padded_bytes(&self, slot_size: usize, write_size: usize) -> usize {
+        let mut size = Self::HEADER_SIZE.next_multiple_of(write_size);
         let mut remaining_data = self.len as usize;
-        let mut remaining_space = SLOT_SIZE - Self::HEADER_SIZE;
+        let mut remaining_space = slot_size - size;
 
         loop {
             let this_round = remaining_space.min(remaining_data);
@@ -147,8 +237,9 @@ impl Slot {
                 break;
             }
 
-            size = size.saturating_add(1); // for the next slot's header byte
-            remaining_space = SLOT_SIZE - 1;
+            // for the next slot's header byte, padded to the programming unit
+            size = size.saturating_add(write_size);
+            remaining_space = slot_size - write_size;
         }
 
         size
@@ -164,13 +255,25 @@ impl Slot {
     /// * `SLOT_SIZE` - The size of each slot in bytes
     /// * `SLOT_COUNT` - The total number of slots available
     pub fn next_slot<const SLOT_SIZE: usize, const SLOT_COUNT: usize>(&self) -> usize {
-        let used_slots = self.used_bytes::<SLOT_SIZE>().div_ceil(SLOT_SIZE);
+        self.next_padded_slot::<SLOT_SIZE, SLOT_COUNT>(1)
+    }
+
+    /// Calculate the index of the next free slot after this savegame with padding
+    ///
+    /// Like [`Slot::next_slot`], but for flash with a programming unit of `write_size`.
+    pub(crate) fn next_padded_slot<const SLOT_SIZE: usize, const SLOT_COUNT: usize>(
+        &self,
+        write_size: usize,
+    ) -> usize {
+        let used_slots = self.padded_bytes(SLOT_SIZE, write_size).div_ceil(SLOT_SIZE);
         self.idx.saturating_add(used_slots) % SLOT_COUNT
     }
 
     /// Serialize the slot header to bytes for writing to flash
     ///
-    /// The format is: checksum (4 bytes) + length (4 bytes) + prev checksum (4 bytes)
+    /// The format is: checksum (4 bytes) + length (4 bytes) + prev checksum (4 bytes).
+    /// The flags are stored in the most significant byte of the length, limiting
+    /// savegames to 16MiB.
     pub fn to_bytes(&self) -> [u8; Self::HEADER_SIZE] {
         let mut buf = [0u8; Self::HEADER_SIZE];
 
@@ -178,7 +281,8 @@ impl Slot {
             arrayref::mut_array_refs![&mut buf, Chksum::SIZE, LENGTH_SIZE, Chksum::SIZE];
 
         chksum.copy_from_slice(&self.chksum.to_bytes());
-        len.copy_from_slice(&self.len.to_be_bytes());
+        let len_field = (u32::from(self.flags) << 24) | (self.len & LENGTH_MASK);
+        len.copy_from_slice(&len_field.to_be_bytes());
         prev.copy_from_slice(&self.prev.to_bytes());
 
         buf
@@ -197,8 +301,9 @@ impl Slot {
         Self {
             idx,
             chksum: Chksum::from_bytes(*chksum),
-            len: u32::from_be_bytes(*len),
+            len: u32::from_be_bytes(*len) & LENGTH_MASK,
             prev: Chksum::from_bytes(*prev),
+            flags: len[0],
         }
     }
 }
@@ -225,6 +330,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_slot_flags() {
+        let mut slot = Slot::create(0, Chksum::zero(), b"hello");
+        slot.flags = Slot::FLAG_COMPRESSED;
+        let bytes = slot.to_bytes();
+        assert_eq!(bytes[4..8], [1, 0, 0, 5]);
+        assert_eq!(Slot::from_bytes(0, bytes), slot);
+    }
+
     #[test]
     fn test_slot_size_small() {
         let slot = Slot::create(0, Chksum::zero(), b"ohai!");
@@ -263,4 +377,21 @@ mod tests {
         );
         assert_eq!(slot.next_slot::<SLOT_SIZE, SLOT_COUNT>(), 3);
     }
+
+    #[test]
+    fn test_slot_padded_spill_over() {
+        let bytes = [b'B'; SLOT_SIZE];
+        let slot = Slot::create(0, Chksum::zero(), &bytes);
+        assert_eq!(
+            slot.padded_bytes(SLOT_SIZE, 8),
+            // Header padded to 16 bytes, then one padded continue-header
+            SLOT_SIZE + 8 + 16,
+        );
+        assert_eq!(slot.next_padded_slot::<SLOT_SIZE, SLOT_COUNT>(8), 2);
+
+        let bytes = [b'B'; SLOT_SIZE - 16];
+        let slot = Slot::create(0, Chksum::zero(), &bytes);
+        assert_eq!(slot.padded_bytes(SLOT_SIZE, 16), SLOT_SIZE);
+        assert_eq!(slot.next_padded_slot::<SLOT_SIZE, SLOT_COUNT>(16), 1);
+    }
 }
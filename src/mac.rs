@@ -0,0 +1,119 @@
+//! Savegame authentication
+//!
+//! This module provides the keyed MAC appended to savegames of a [`Storage`](crate::storage::Storage)
+//! created with [`Storage::with_key`](crate::storage::Storage::with_key). Available with the `mac`
+//! feature.
+//!
+//! The DJB2 checksum only detects accidental corruption and is trivially recomputed after
+//! editing a savegame. The MAC is HMAC-SHA256 over the slot header and the savegame data,
+//! truncated to [`TAG_SIZE`] bytes. Without the key, modified savegames can't be made to pass
+//! verification, so they are ignored by [`Storage::scan`](crate::storage::Storage::scan) and
+//! [`Storage::read`](crate::storage::Storage::read).
+//!
+//! Authentication doesn't prevent replaying an older savegame that was read out before.
+
+use core::fmt;
+use hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+
+/// Size of the MAC appended to each savegame in bytes
+pub const TAG_SIZE: usize = 16;
+
+/// Secret key to authenticate savegames with
+///
+/// The key should be unique per device, e.g. derived from a chip ID with a secret kept in
+/// read-protected memory. The key isn't printed by its [`Debug`](fmt::Debug) implementation,
+/// and keys can't be compared, which wouldn't take constant time.
+#[derive(Clone)]
+pub struct Key([u8; 32]);
+
+impl Key {
+    /// Create a key from its raw bytes
+    pub const fn new(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+
+    /// The raw bytes of the key
+    #[cfg(feature = "encrypt")]
+    pub(crate) const fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+/// Incremental MAC computation over a savegame
+pub(crate) struct Mac(Hmac<Sha256>);
+
+impl Mac {
+    /// Start a MAC over the savegame with the given (unencoded) slot header
+    pub(crate) fn new(key: &Key, header: &[u8]) -> Self {
+        // HMAC accepts keys of any length
+        let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(&key.0) else {
+            unreachable!()
+        };
+        mac.update(header);
+        Self(mac)
+    }
+
+    /// Add savegame data
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Compute the full, untruncated MAC
+    #[cfg(feature = "encrypt")]
+    pub(crate) fn finalize_full(self) -> [u8; 32] {
+        self.0.finalize().into_bytes().into()
+    }
+
+    /// Compute the truncated tag
+    pub(crate) fn finalize(self) -> [u8; TAG_SIZE] {
+        let mut tag = [0u8; TAG_SIZE];
+        tag.copy_from_slice(&self.0.finalize().into_bytes()[..TAG_SIZE]);
+        tag
+    }
+
+    /// Compare against a stored tag in constant time
+    pub(crate) fn verify(self, tag: &[u8; TAG_SIZE]) -> bool {
+        self.0.verify_truncated_left(tag).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag() {
+        let key = Key::new([0x42; 32]);
+        let mut mac = Mac::new(&key, b"header");
+        mac.update(b"hello ");
+        mac.update(b"world");
+        let tag = mac.finalize();
+
+        // Data fed in chunks authenticates the same
+        let mut mac = Mac::new(&key, b"header");
+        mac.update(b"hello world");
+        assert!(mac.verify(&tag));
+
+        let mut mac = Mac::new(&key, b"header");
+        mac.update(b"hello World");
+        assert!(!mac.verify(&tag));
+
+        let mut mac = Mac::new(&Key::new([0x43; 32]), b"header");
+        mac.update(b"hello world");
+        assert!(!mac.verify(&tag));
+    }
+
+    #[test]
+    fn test_key_debug() {
+        extern crate std;
+        let key = Key::new([0x42; 32]);
+        assert_eq!(std::format!("{key:?}"), "Key(..)");
+    }
+}
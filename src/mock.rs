@@ -6,6 +6,7 @@
 //! - [`MockFlash`]: Simple byte-addressable mock flash (like EEPROM)
 //! - [`SectorMockFlash`]: Sector-based mock flash (like NOR flash)
 //! - [`MeasuredMockFlash`]: Mock flash that tracks operation statistics
+//! - [`AlignedMockFlash`]: Sector-based mock flash with a programming unit (like MCU flash)
 
 use crate::storage::Flash;
 use core::convert::Infallible;
@@ -13,27 +14,31 @@ use core::convert::Infallible;
 /// Simple mock flash device with byte-level operations
 ///
 /// Simulates EEPROM-like flash where individual bytes can be written.
-/// Initialized with all bytes set to 0xFF (erased state).
+/// Initialized with all bytes set to `ERASED` (0xFF by default).
 #[derive(Debug, PartialEq)]
-pub struct MockFlash<const SIZE: usize> {
+pub struct MockFlash<const SIZE: usize, const ERASED: u8 = 0xFF> {
     data: [u8; SIZE],
 }
 
-impl<const SIZE: usize> MockFlash<SIZE> {
+impl<const SIZE: usize, const ERASED: u8> MockFlash<SIZE, ERASED> {
     pub const fn new() -> Self {
-        Self { data: [0xFF; SIZE] }
+        Self {
+            data: [ERASED; SIZE],
+        }
     }
 }
 
-impl<const SIZE: usize> Default for MockFlash<SIZE> {
+impl<const SIZE: usize, const ERASED: u8> Default for MockFlash<SIZE, ERASED> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<const SIZE: usize> Flash for MockFlash<SIZE> {
+impl<const SIZE: usize, const ERASED: u8> Flash for MockFlash<SIZE, ERASED> {
     type Error = Infallible;
 
+    const ERASED: u8 = ERASED;
+
     fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
         let addr = addr as usize;
         let len = buf.len();
@@ -42,6 +47,10 @@ impl<const SIZE: usize> Flash for MockFlash<SIZE> {
     }
 
     fn write(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.write_slice(addr, data)
+    }
+
+    fn write_slice(&mut self, addr: u32, data: &[u8]) -> Result<(), Self::Error> {
         let addr = addr as usize;
         let len = data.len();
         self.data[addr..addr + len].copy_from_slice(data);
@@ -49,7 +58,7 @@ impl<const SIZE: usize> Flash for MockFlash<SIZE> {
     }
 
     fn erase(&mut self, addr: u32) -> Result<(), Self::Error> {
-        self.data[addr as usize] = 0xFF;
+        self.data[addr as usize] = ERASED;
         Ok(())
     }
 }
@@ -94,6 +103,8 @@ impl<const SECTOR_SIZE: usize, const SECTOR_COUNT: usize> Flash
 {
     type Error = Infallible;
 
+    const ERASE_SIZE: usize = SECTOR_SIZE;
+
     fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
         let (sector, offset) = Self::div_rem(addr);
         buf.copy_from_slice(&self.data[sector][offset..offset + buf.len()]);
@@ -101,6 +112,10 @@ impl<const SECTOR_SIZE: usize, const SECTOR_COUNT: usize> Flash
     }
 
     fn write(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.write_slice(addr, buf)
+    }
+
+    fn write_slice(&mut self, addr: u32, buf: &[u8]) -> Result<(), Self::Error> {
         let (sector, offset) = Self::div_rem(addr);
 
         let mut flash = self.data[sector][offset..offset + buf.len()].iter_mut();
@@ -119,12 +134,97 @@ impl<const SECTOR_SIZE: usize, const SECTOR_COUNT: usize> Flash
     }
 }
 
+/// Sector-based mock flash device with a programming unit
+///
+/// Simulates internal MCU flash that can only program aligned multiples of
+/// `WRITE_SIZE` bytes, and can't program the same unit twice until its sector
+/// is erased. Violations are reported as [`AlignedMockError`].
+#[derive(Debug, PartialEq)]
+pub struct AlignedMockFlash<
+    const WRITE_SIZE: usize,
+    const SECTOR_SIZE: usize,
+    const SECTOR_COUNT: usize,
+> {
+    data: [[u8; SECTOR_SIZE]; SECTOR_COUNT],
+    programmed: [[bool; SECTOR_SIZE]; SECTOR_COUNT],
+}
+
+/// Errors reported by [`AlignedMockFlash`]
+#[derive(Debug, PartialEq)]
+pub enum AlignedMockError {
+    /// Address or length of a write is not a multiple of the programming unit
+    Misaligned,
+    /// A programming unit was written twice without erasing it
+    DoubleWrite,
+}
+
+impl<const WRITE_SIZE: usize, const SECTOR_SIZE: usize, const SECTOR_COUNT: usize>
+    AlignedMockFlash<WRITE_SIZE, SECTOR_SIZE, SECTOR_COUNT>
+{
+    pub const fn new() -> Self {
+        Self {
+            data: [[0xFF; SECTOR_SIZE]; SECTOR_COUNT],
+            programmed: [[false; SECTOR_SIZE]; SECTOR_COUNT],
+        }
+    }
+}
+
+impl<const WRITE_SIZE: usize, const SECTOR_SIZE: usize, const SECTOR_COUNT: usize> Default
+    for AlignedMockFlash<WRITE_SIZE, SECTOR_SIZE, SECTOR_COUNT>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const WRITE_SIZE: usize, const SECTOR_SIZE: usize, const SECTOR_COUNT: usize> Flash
+    for AlignedMockFlash<WRITE_SIZE, SECTOR_SIZE, SECTOR_COUNT>
+{
+    type Error = AlignedMockError;
+
+    const ERASE_SIZE: usize = SECTOR_SIZE;
+    const WRITE_SIZE: usize = WRITE_SIZE;
+
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let (sector, offset) = SectorMockFlash::<SECTOR_SIZE, SECTOR_COUNT>::div_rem(addr);
+        buf.copy_from_slice(&self.data[sector][offset..offset + buf.len()]);
+        Ok(())
+    }
+
+    fn write(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        if !(addr as usize).is_multiple_of(WRITE_SIZE) || !buf.len().is_multiple_of(WRITE_SIZE) {
+            return Err(AlignedMockError::Misaligned);
+        }
+
+        let (sector, offset) = SectorMockFlash::<SECTOR_SIZE, SECTOR_COUNT>::div_rem(addr);
+        let range = offset..offset + buf.len();
+        if self.programmed[sector][range.clone()].contains(&true) {
+            return Err(AlignedMockError::DoubleWrite);
+        }
+
+        self.programmed[sector][range.clone()].fill(true);
+        for (flash_byte, byte) in self.data[sector][range].iter_mut().zip(buf) {
+            *flash_byte &= *byte;
+        }
+
+        Ok(())
+    }
+
+    fn erase(&mut self, addr: u32) -> Result<(), Self::Error> {
+        let (sector, _offset) = SectorMockFlash::<SECTOR_SIZE, SECTOR_COUNT>::div_rem(addr);
+        self.data[sector] = [0xFF; SECTOR_SIZE];
+        self.programmed[sector] = [false; SECTOR_SIZE];
+        Ok(())
+    }
+}
+
 /// Mock flash device that tracks operation statistics
 ///
 /// Wraps [`MockFlash`] and counts the number of bytes read/written and erase operations.
-/// Useful for analyzing storage efficiency and optimization.
+/// Useful for analyzing storage efficiency and optimization. With `NEEDS_ERASE`
+/// set to `false` it behaves like memory without erase cycles (e.g. FRAM).
 #[derive(Debug, Default)]
-pub struct MeasuredMockFlash<const SIZE: usize> {
+pub struct MeasuredMockFlash<const SIZE: usize, const NEEDS_ERASE: bool = true> {
     flash: MockFlash<SIZE>,
     /// Statistics for all flash operations performed
     pub stats: MeasuredStats,
@@ -143,23 +243,29 @@ pub struct MeasuredStats {
     pub erase: usize,
 }
 
-impl<const SIZE: usize> MeasuredMockFlash<SIZE> {
+impl<const SIZE: usize, const NEEDS_ERASE: bool> MeasuredMockFlash<SIZE, NEEDS_ERASE> {
     pub fn new() -> Self {
         Self::default()
     }
 }
 
-impl<const SIZE: usize> Flash for MeasuredMockFlash<SIZE> {
+impl<const SIZE: usize, const NEEDS_ERASE: bool> Flash for MeasuredMockFlash<SIZE, NEEDS_ERASE> {
     type Error = Infallible;
 
+    const NEEDS_ERASE: bool = NEEDS_ERASE;
+
     fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
         self.stats.read = self.stats.read.saturating_add(buf.len());
         self.flash.read(addr, buf)
     }
 
     fn write(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.write_slice(addr, data)
+    }
+
+    fn write_slice(&mut self, addr: u32, data: &[u8]) -> Result<(), Self::Error> {
         self.stats.write = self.stats.write.saturating_add(data.len());
-        self.flash.write(addr, data)
+        self.flash.write_slice(addr, data)
     }
 
     fn erase(&mut self, addr: u32) -> Result<(), Self::Error> {
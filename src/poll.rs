@@ -0,0 +1,136 @@
+//! Write completion polling for EEPROM and NOR flash
+//!
+//! EEPROM chips enter an internally-timed write cycle after every write, NOR flash reports
+//! program and erase operations as busy until they are done. This module provides strategies
+//! that bound how long a backend waits for completion, so a missing chip or a stuck bus
+//! surfaces as a timeout error instead of hanging forever.
+//!
+//! - [`MaxAttempts`]: Give up after a fixed number of polls
+//! - [`Timeout`]: Wait between polls with a [`DelayNs`] and give up after a timeout
+
+use embedded_hal::delay::DelayNs;
+
+/// Strategy for waiting on a write cycle to complete
+pub trait Poll {
+    /// Wait before the next poll
+    ///
+    /// `attempt` is the number of polls that didn't report completion so far,
+    /// starting at 1. Returns `false` to give up.
+    fn wait(&mut self, attempt: u32) -> bool;
+}
+
+/// Give up after a fixed number of polls, without waiting in between
+///
+/// The time this allows depends on how fast the bus and the core poll, so the
+/// count has to be sized for the fastest setup. Prefer [`Timeout`] to bound the
+/// wait by time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaxAttempts(pub u32);
+
+impl Poll for MaxAttempts {
+    fn wait(&mut self, attempt: u32) -> bool {
+        attempt < self.0
+    }
+}
+
+/// Wait a fixed interval between polls and give up after a timeout
+#[derive(Debug)]
+pub struct Timeout<D> {
+    delay: D,
+    interval_us: u32,
+    timeout_us: u32,
+}
+
+impl<D: DelayNs> Timeout<D> {
+    /// Create a new timeout strategy
+    ///
+    /// # Arguments
+    ///
+    /// * `delay` - The delay provider to wait with
+    /// * `interval_us` - The time to wait between polls in microseconds
+    /// * `timeout_us` - The total time to wait before giving up in microseconds
+    pub const fn new(delay: D, interval_us: u32, timeout_us: u32) -> Self {
+        Self {
+            delay,
+            interval_us,
+            timeout_us,
+        }
+    }
+
+    /// Consume the strategy and return the delay provider
+    pub fn into_inner(self) -> D {
+        self.delay
+    }
+}
+
+impl<D: DelayNs> Poll for Timeout<D> {
+    fn wait(&mut self, attempt: u32) -> bool {
+        let elapsed = self.interval_us.saturating_mul(attempt - 1);
+        if elapsed >= self.timeout_us {
+            return false;
+        }
+        self.delay.delay_us(self.interval_us);
+        true
+    }
+}
+
+/// Poll `ready` until it reports completion
+///
+/// Returns `Ok(false)` if the strategy gave up first.
+pub(crate) fn until<P: Poll, E>(
+    poll: &mut P,
+    mut ready: impl FnMut() -> Result<bool, E>,
+) -> Result<bool, E> {
+    let mut attempt = 0u32;
+    loop {
+        if ready()? {
+            return Ok(true);
+        }
+        attempt = attempt.saturating_add(1);
+        if !poll.wait(attempt) {
+            return Ok(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+    use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction};
+
+    #[test]
+    fn test_max_attempts() {
+        let mut polls = 0;
+        let Ok(ready) = until(&mut MaxAttempts(3), || {
+            polls += 1;
+            Ok::<_, Infallible>(false)
+        });
+        assert!(!ready);
+        assert_eq!(polls, 3);
+
+        let mut polls = 0;
+        let Ok(ready) = until(&mut MaxAttempts(3), || {
+            polls += 1;
+            Ok::<_, Infallible>(polls == 2)
+        });
+        assert!(ready);
+        assert_eq!(polls, 2);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let delay = CheckedDelay::new(&[Transaction::delay_us(100), Transaction::delay_us(100)]);
+        let mut timeout = Timeout::new(delay, 100, 200);
+
+        let mut polls = 0;
+        let Ok(ready) = until(&mut timeout, || {
+            polls += 1;
+            Ok::<_, Infallible>(false)
+        });
+        assert!(!ready);
+        assert_eq!(polls, 3);
+
+        timeout.into_inner().done();
+    }
+}
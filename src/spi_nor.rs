@@ -0,0 +1,719 @@
+//! SPI NOR flash support
+//!
+//! This module provides a [`Flash`](crate::storage::Flash) implementation for JEDEC compatible SPI
+//! NOR flash chips like the Winbond W25Q series. Available with the `spi-nor` feature.
+//!
+//! The chip is driven directly through an embedded-hal 1.0 [`SpiDevice`] and can be used side
+//! by side with the `w25q` feature, which uses embedded-hal 0.2.
+//!
+//! Other vendors' parts (e.g. GD25, MX25 or IS25) are supported by discovering their geometry
+//! from the JEDEC SFDP tables at runtime, see [`SpiNor::probe`].
+
+use crate::poll::{self, Poll};
+use crate::storage::Flash;
+use embedded_hal::spi::{Operation, SpiDevice};
+
+/// Read data from memory
+const READ: u8 = 0x03;
+/// Program up to a page of memory
+const PAGE_PROGRAM: u8 = 0x02;
+/// Set the write enable latch
+const WREN: u8 = 0x06;
+/// Read status register 1
+const RDSR: u8 = 0x05;
+/// Erase a 4KiB sector
+const SECTOR_ERASE: u8 = 0x20;
+/// Erase a 32KiB block
+const BLOCK_ERASE_32K: u8 = 0x52;
+/// Erase a 64KiB block
+const BLOCK_ERASE_64K: u8 = 0xD8;
+/// Erase the whole chip
+const CHIP_ERASE: u8 = 0xC7;
+/// Read manufacturer and device ID
+const READ_JEDEC_ID: u8 = 0x9F;
+/// Read the serial flash discoverable parameters
+const READ_SFDP: u8 = 0x5A;
+
+/// Busy bit of the status register
+const STATUS_BUSY: u8 = 0x01;
+
+/// Default size of a page in bytes, page program wraps around within a page
+pub const PAGE_SIZE: u32 = 256;
+/// Size of the smallest erasable sector in bytes
+pub const SECTOR_SIZE: u32 = 4096;
+
+/// `SFDP` signature at the start of the SFDP tables
+const SFDP_SIGNATURE: [u8; 4] = *b"SFDP";
+/// Number of basic flash parameter table DWORDs used by this driver
+const BFPT_DWORDS: usize = 11;
+/// Largest capacity reachable with 3-byte addresses
+const MAX_CAPACITY: u32 = 1 << 24;
+
+/// Errors of the SPI NOR flash backend
+#[derive(Debug)]
+pub enum Error<E> {
+    /// SPI bus error
+    Spi(E),
+    /// The chip didn't complete a program or erase operation in time
+    Timeout,
+    /// The chip has no valid SFDP basic flash parameter table
+    Sfdp,
+    /// The chip doesn't support 3-byte addresses or 4KiB sector erase, or its pages don't line
+    /// up with the slots
+    Unsupported,
+    /// The storage area doesn't fit into the detected capacity
+    Capacity,
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(err: E) -> Self {
+        Self::Spi(err)
+    }
+}
+
+/// Erase operation supported by the chip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EraseType {
+    /// Size of the erased block in bytes
+    pub size: u32,
+    /// Instruction to erase a block
+    pub opcode: u8,
+}
+
+/// Geometry of a SPI NOR flash chip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Geometry {
+    /// JEDEC manufacturer ID, memory type and capacity code
+    pub jedec_id: [u8; 3],
+    /// Usable capacity in bytes, limited to 16MiB by 3-byte addressing
+    pub capacity: u32,
+    /// Size of a page in bytes
+    pub page_size: u32,
+    /// Supported erase operations, as listed in the SFDP tables
+    pub erase_types: [Option<EraseType>; 4],
+}
+
+impl Geometry {
+    /// Geometry assumed until the chip is probed, matching the W25Q series
+    ///
+    /// The capacity is unknown and set to the largest one reachable with 3-byte addresses.
+    pub const DEFAULT: Self = Self {
+        jedec_id: [0; 3],
+        capacity: MAX_CAPACITY,
+        page_size: PAGE_SIZE,
+        erase_types: [
+            Some(EraseType {
+                size: SECTOR_SIZE,
+                opcode: SECTOR_ERASE,
+            }),
+            Some(EraseType {
+                size: 32 * 1024,
+                opcode: BLOCK_ERASE_32K,
+            }),
+            Some(EraseType {
+                size: 64 * 1024,
+                opcode: BLOCK_ERASE_64K,
+            }),
+            None,
+        ],
+    };
+
+    /// Parse the basic flash parameter table
+    ///
+    /// `bfpt` holds the first DWORDs of the table, `dwords` is the length of the table
+    /// according to its parameter header.
+    fn parse(jedec_id: [u8; 3], bfpt: &[u32; BFPT_DWORDS], dwords: usize) -> Option<Self> {
+        // JESD216 requires at least 9 DWORDs
+        if dwords < 9 {
+            return None;
+        }
+
+        let density = bfpt[1];
+        let capacity = if density & (1 << 31) == 0 {
+            (u64::from(density) + 1) / 8
+        } else {
+            1u64.checked_shl((density & !(1 << 31)).saturating_sub(3))
+                .unwrap_or(u64::MAX)
+        };
+        let capacity = capacity.min(u64::from(MAX_CAPACITY)) as u32;
+
+        let mut erase_types = [None; 4];
+        for (i, erase_type) in erase_types.iter_mut().enumerate() {
+            let [size, opcode] = ((bfpt[7 + i / 2] >> (16 * (i % 2))) as u16).to_le_bytes();
+            if size != 0 && size < 32 {
+                *erase_type = Some(EraseType {
+                    size: 1 << size,
+                    opcode,
+                });
+            }
+        }
+
+        // Page size is only listed since JESD216A
+        let page_size = if dwords >= 11 {
+            1 << ((bfpt[10] >> 4) & 0xF)
+        } else {
+            PAGE_SIZE
+        };
+
+        Some(Self {
+            jedec_id,
+            capacity,
+            page_size,
+            erase_types,
+        })
+    }
+
+    /// Find the erase operation for blocks of `size` bytes
+    pub fn erase_type(&self, size: u32) -> Option<EraseType> {
+        self.erase_types
+            .iter()
+            .flatten()
+            .find(|erase_type| erase_type.size == size)
+            .copied()
+    }
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// JEDEC compatible SPI NOR flash chip with 3-byte addresses
+///
+/// # Type Parameters
+///
+/// * `SPI` - The SPI device the chip is connected to
+/// * `P` - The strategy to poll for completion of program and erase operations with. Erase
+///   operations take up to several hundred milliseconds, chip erase even minutes, so a
+///   [`Timeout`](crate::poll::Timeout) is recommended.
+#[derive(Debug)]
+pub struct SpiNor<SPI, P> {
+    spi: SPI,
+    poll: P,
+    geometry: Geometry,
+}
+
+impl<SPI: SpiDevice, P: Poll> SpiNor<SPI, P> {
+    /// Create a new driver for a SPI NOR flash chip
+    ///
+    /// The chip is assumed to have the [`Geometry::DEFAULT`] of the W25Q series, and to be large
+    /// enough for the storage area. Use [`probe`](Self::probe) to detect and check the geometry,
+    /// which is required for other chips.
+    pub const fn new(spi: SPI, poll: P) -> Self {
+        Self {
+            spi,
+            poll,
+            geometry: Geometry::DEFAULT,
+        }
+    }
+
+    /// Consume the driver and return the underlying SPI device and polling strategy
+    pub fn into_inner(self) -> (SPI, P) {
+        (self.spi, self.poll)
+    }
+
+    /// Encode a command with its 3-byte address
+    const fn command(opcode: u8, addr: u32) -> [u8; 4] {
+        let [_, a2, a1, a0] = addr.to_be_bytes();
+        [opcode, a2, a1, a0]
+    }
+
+    /// Read the JEDEC manufacturer ID, memory type and capacity code
+    pub fn read_jedec_id(&mut self) -> Result<[u8; 3], Error<SPI::Error>> {
+        let mut id = [0u8; 3];
+        self.spi
+            .transaction(&mut [Operation::Write(&[READ_JEDEC_ID]), Operation::Read(&mut id)])?;
+        Ok(id)
+    }
+
+    /// Read from the SFDP tables
+    fn read_sfdp(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Error<SPI::Error>> {
+        let [_, a2, a1, a0] = addr.to_be_bytes();
+        // The address is followed by 8 dummy cycles
+        let cmd = [READ_SFDP, a2, a1, a0, 0];
+        self.spi
+            .transaction(&mut [Operation::Write(&cmd), Operation::Read(buf)])?;
+        Ok(())
+    }
+
+    /// Detect the geometry of the chip from its JEDEC ID and SFDP tables
+    ///
+    /// The detected geometry is checked against a storage area of `SLOT_COUNT` slots of
+    /// `SLOT_SIZE` bytes and used for all further operations. Fails with [`Error::Sfdp`] if the
+    /// chip has no SFDP tables, with [`Error::Unsupported`] if it can't be used with 3-byte
+    /// addresses and 4KiB sector erases or its pages don't line up with the slots, and with
+    /// [`Error::Capacity`] if the storage area doesn't fit into the chip.
+    pub fn probe<const SLOT_SIZE: usize, const SLOT_COUNT: usize>(
+        &mut self,
+    ) -> Result<&Geometry, Error<SPI::Error>> {
+        let jedec_id = self.read_jedec_id()?;
+
+        // SFDP header followed by the first parameter header
+        let mut header = [0u8; 16];
+        self.read_sfdp(0, &mut header)?;
+        let (sfdp, param) = header.split_at(8);
+        if sfdp[..4] != SFDP_SIGNATURE {
+            return Err(Error::Sfdp);
+        }
+        // The first parameter table is always the basic flash parameter table
+        if param[0] != 0x00 || param[7] != 0xFF {
+            return Err(Error::Sfdp);
+        }
+        let dwords = usize::from(param[3]);
+        let ptr = u32::from_le_bytes([param[4], param[5], param[6], 0]);
+
+        let mut buf = [0u8; BFPT_DWORDS * 4];
+        let len = dwords.min(BFPT_DWORDS) * 4;
+        self.read_sfdp(ptr, &mut buf[..len])?;
+        let bfpt = core::array::from_fn(|i| {
+            u32::from_le_bytes([buf[i * 4], buf[i * 4 + 1], buf[i * 4 + 2], buf[i * 4 + 3]])
+        });
+
+        let geometry = Geometry::parse(jedec_id, &bfpt, dwords).ok_or(Error::Sfdp)?;
+        // Chips that only support 4-byte addresses can't be used
+        let address_bytes = (bfpt[0] >> 17) & 0b11;
+        if address_bytes == 0b10 {
+            return Err(Error::Unsupported);
+        }
+        Self::validate::<SLOT_SIZE, SLOT_COUNT>(&geometry)?;
+        self.geometry = geometry;
+        Ok(&self.geometry)
+    }
+
+    /// The geometry of the chip, as detected by [`probe`](Self::probe)
+    pub const fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    /// Check that a storage area with the given slot size and count can be used on a chip
+    fn validate<const SLOT_SIZE: usize, const SLOT_COUNT: usize>(
+        geometry: &Geometry,
+    ) -> Result<(), Error<SPI::Error>> {
+        // Slots are erased in 4KiB sectors and programmed in pages
+        let page_size = geometry.page_size as usize;
+        if geometry.erase_type(SECTOR_SIZE).is_none()
+            || !(SECTOR_SIZE as usize).is_multiple_of(SLOT_SIZE)
+            || !page_size.is_power_of_two()
+            || page_size > SECTOR_SIZE as usize
+            || !(SLOT_SIZE.is_multiple_of(page_size) || page_size.is_multiple_of(SLOT_SIZE))
+        {
+            return Err(Error::Unsupported);
+        }
+        let space = (SLOT_SIZE as u64).saturating_mul(SLOT_COUNT as u64);
+        if space > u64::from(geometry.capacity) {
+            return Err(Error::Capacity);
+        }
+        Ok(())
+    }
+
+    /// Poll the status register until the chip is no longer busy
+    fn wait_ready(&mut self) -> Result<(), Error<SPI::Error>> {
+        let spi = &mut self.spi;
+        let ready = poll::until(&mut self.poll, || {
+            let mut status = [0u8; 1];
+            spi.transaction(&mut [Operation::Write(&[RDSR]), Operation::Read(&mut status)])?;
+            Ok::<_, SPI::Error>(status[0] & STATUS_BUSY == 0)
+        })?;
+        if ready { Ok(()) } else { Err(Error::Timeout) }
+    }
+
+    /// Run a program or erase command and wait for it to complete
+    fn execute(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Error<SPI::Error>> {
+        // The write enable latch is reset after every program or erase operation
+        self.spi.write(&[WREN])?;
+        self.spi.transaction(operations)?;
+        self.wait_ready()
+    }
+
+    /// Erase the 4KiB sector containing `addr`
+    ///
+    /// Fails with [`Error::Unsupported`] if the chip has no 4KiB sector erase.
+    pub fn erase_sector(&mut self, addr: u32) -> Result<(), Error<SPI::Error>> {
+        let addr = addr - addr % SECTOR_SIZE;
+        let erase_type = self
+            .geometry
+            .erase_type(SECTOR_SIZE)
+            .ok_or(Error::Unsupported)?;
+        self.execute(&mut [Operation::Write(&Self::command(erase_type.opcode, addr))])
+    }
+
+    /// Erase the region `0..len`, using the largest aligned block erases available
+    ///
+    /// `len` is rounded up to whole 4KiB sectors.
+    pub fn erase_region(&mut self, len: u32) -> Result<(), Error<SPI::Error>> {
+        let mut addr = 0;
+        while addr < len {
+            let remaining = len - addr;
+            // Block sizes are powers of two, so the largest aligned block that fits is the best
+            let erase_type = self
+                .geometry
+                .erase_types
+                .iter()
+                .flatten()
+                .copied()
+                .filter(|erase_type| {
+                    erase_type.size >= SECTOR_SIZE
+                        && addr.is_multiple_of(erase_type.size)
+                        && remaining >= erase_type.size
+                })
+                .max_by_key(|erase_type| erase_type.size);
+
+            if let Some(erase_type) = erase_type {
+                let cmd = Self::command(erase_type.opcode, addr);
+                self.execute(&mut [Operation::Write(&cmd)])?;
+                addr = addr.saturating_add(erase_type.size);
+            } else {
+                self.erase_sector(addr)?;
+                addr = addr.saturating_add(SECTOR_SIZE);
+            }
+        }
+        Ok(())
+    }
+
+    /// Erase the whole chip
+    pub fn erase_chip(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.execute(&mut [Operation::Write(&[CHIP_ERASE])])
+    }
+}
+
+/// Flash trait implementation for SPI NOR flash chips
+impl<SPI: SpiDevice, P: Poll> Flash for SpiNor<SPI, P> {
+    type Error = Error<SPI::Error>;
+
+    const ERASE_SIZE: usize = SECTOR_SIZE as usize;
+
+    fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let cmd = Self::command(READ, addr);
+        self.spi
+            .transaction(&mut [Operation::Write(&cmd), Operation::Read(buf)])?;
+        Ok(())
+    }
+
+    fn write(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.write_slice(addr, data)
+    }
+
+    fn write_slice(&mut self, mut addr: u32, mut data: &[u8]) -> Result<(), Self::Error> {
+        // Writes crossing a page boundary would wrap around within the page
+        let page_size = self.geometry.page_size;
+        while !data.is_empty() {
+            let page_remaining = page_size - addr % page_size;
+            let write_size = data.len().min(page_remaining as usize);
+            let (to_write, remaining) = data.split_at(write_size);
+            let cmd = Self::command(PAGE_PROGRAM, addr);
+            self.execute(&mut [Operation::Write(&cmd), Operation::Write(to_write)])?;
+            addr = addr.saturating_add(write_size as u32);
+            data = remaining;
+        }
+        Ok(())
+    }
+
+    fn erase(&mut self, addr: u32) -> Result<(), Self::Error> {
+        self.erase_sector(addr)
+    }
+
+    fn erase_all(&mut self, len: u32) -> Result<(), Self::Error> {
+        self.erase_region(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::poll::MaxAttempts;
+    use embedded_hal_mock::eh1::spi::{Mock, Transaction};
+    use std::vec;
+    use std::vec::Vec;
+
+    fn command(ops: Vec<Vec<u8>>) -> Vec<Transaction<u8>> {
+        let mut expectations = vec![
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![WREN]),
+            Transaction::transaction_end(),
+            Transaction::transaction_start(),
+        ];
+        expectations.extend(ops.into_iter().map(Transaction::write_vec));
+        expectations.push(Transaction::transaction_end());
+        expectations
+    }
+
+    fn read_status(status: &[u8]) -> Vec<Transaction<u8>> {
+        status
+            .iter()
+            .flat_map(|status| {
+                [
+                    Transaction::transaction_start(),
+                    Transaction::write_vec(vec![RDSR]),
+                    Transaction::read_vec(vec![*status]),
+                    Transaction::transaction_end(),
+                ]
+            })
+            .collect()
+    }
+
+    fn read_sfdp(addr: u32, data: Vec<u8>) -> [Transaction<u8>; 4] {
+        let [_, a2, a1, a0] = addr.to_be_bytes();
+        [
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![READ_SFDP, a2, a1, a0, 0]),
+            Transaction::read_vec(data),
+            Transaction::transaction_end(),
+        ]
+    }
+
+    /// SFDP tables of a 64Mbit chip with a 16 DWORD basic flash parameter table
+    fn sfdp_expectations(dword1: u32, page_size: u32) -> Vec<Transaction<u8>> {
+        let header = [
+            b"SFDP".as_slice(),
+            &[0x06, 0x01, 0x00, 0xFF],
+            // Basic flash parameter table at 0x30
+            &[0x00, 0x06, 0x01, 16, 0x30, 0x00, 0x00, 0xFF],
+        ]
+        .concat();
+        let bfpt = [
+            dword1,
+            // 64Mbit
+            0x03FF_FFFF,
+            0,
+            0,
+            0,
+            0,
+            0,
+            // 4KiB erase with 0x20, 32KiB erase with 0x52
+            0x520F_200C,
+            // 64KiB erase with 0xD8
+            0x0000_D810,
+            0,
+            (page_size.trailing_zeros() << 4) | 0x9,
+        ];
+
+        let mut expectations = vec![
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![READ_JEDEC_ID]),
+            Transaction::read_vec(vec![0xC8, 0x40, 0x17]),
+            Transaction::transaction_end(),
+        ];
+        expectations.extend(read_sfdp(0, header));
+        expectations.extend(read_sfdp(
+            0x30,
+            bfpt.iter().flat_map(|dword| dword.to_le_bytes()).collect(),
+        ));
+        expectations
+    }
+
+    #[test]
+    fn test_probe() {
+        let mut expectations = sfdp_expectations(0xFFF1_20E5, 64);
+        // Writes are split on the detected page size
+        expectations.extend(command(vec![vec![PAGE_PROGRAM, 0x00, 0x00, 0x3F], vec![1]]));
+        expectations.extend(read_status(&[0]));
+        expectations.extend(command(vec![vec![PAGE_PROGRAM, 0x00, 0x00, 0x40], vec![2]]));
+        expectations.extend(read_status(&[0]));
+        let spi = Mock::new(&expectations);
+        let mut flash = SpiNor::new(spi, MaxAttempts(3));
+
+        let geometry = *flash.probe::<4096, 2048>().unwrap();
+        assert_eq!(
+            geometry,
+            Geometry {
+                jedec_id: [0xC8, 0x40, 0x17],
+                capacity: 8 * 1024 * 1024,
+                page_size: 64,
+                erase_types: [
+                    Some(EraseType {
+                        size: 4096,
+                        opcode: 0x20
+                    }),
+                    Some(EraseType {
+                        size: 32 * 1024,
+                        opcode: 0x52
+                    }),
+                    Some(EraseType {
+                        size: 64 * 1024,
+                        opcode: 0xD8
+                    }),
+                    None,
+                ],
+            }
+        );
+        assert_eq!(flash.geometry(), &geometry);
+
+        flash.write(0x3F, &mut [1, 2]).unwrap();
+
+        flash.into_inner().0.done();
+    }
+
+    #[test]
+    fn test_probe_unsupported() {
+        // 4-byte addresses only
+        let expectations = sfdp_expectations(0xFFF5_20E5, 256);
+        let spi = Mock::new(&expectations);
+        let mut flash = SpiNor::new(spi, MaxAttempts(3));
+
+        let err = flash.probe::<4096, 2>().unwrap_err();
+        assert!(matches!(err, Error::Unsupported));
+        assert_eq!(flash.geometry(), &Geometry::DEFAULT);
+
+        flash.into_inner().0.done();
+    }
+
+    #[test]
+    fn test_probe_capacity() {
+        let expectations = sfdp_expectations(0xFFF1_20E5, 256);
+        let spi = Mock::new(&expectations);
+        let mut flash = SpiNor::new(spi, MaxAttempts(3));
+
+        // One slot more than the 8MiB of the chip
+        let err = flash.probe::<4096, 2049>().unwrap_err();
+        assert!(matches!(err, Error::Capacity));
+        assert_eq!(flash.geometry(), &Geometry::DEFAULT);
+
+        flash.into_inner().0.done();
+    }
+
+    #[test]
+    fn test_probe_slot_geometry() {
+        // No 4KiB sector erase
+        let mut bfpt = [0u32; BFPT_DWORDS];
+        bfpt[1] = 0x03FF_FFFF;
+        bfpt[7] = 0x520F_0000;
+        bfpt[10] = (8 << 4) | 0x9;
+        let geometry = Geometry::parse([0; 3], &bfpt, BFPT_DWORDS).unwrap();
+        let err = SpiNor::<Mock<u8>, MaxAttempts>::validate::<4096, 2>(&geometry).unwrap_err();
+        assert!(matches!(err, Error::Unsupported));
+
+        // Slots must line up with the sectors and pages
+        let geometry = Geometry {
+            page_size: 512,
+            ..Geometry::DEFAULT
+        };
+        SpiNor::<Mock<u8>, MaxAttempts>::validate::<1024, 8>(&geometry).unwrap();
+        SpiNor::<Mock<u8>, MaxAttempts>::validate::<256, 32>(&geometry).unwrap();
+        let err = SpiNor::<Mock<u8>, MaxAttempts>::validate::<768, 16>(&geometry).unwrap_err();
+        assert!(matches!(err, Error::Unsupported));
+    }
+
+    #[test]
+    fn test_probe_no_sfdp() {
+        let mut expectations = vec![
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![READ_JEDEC_ID]),
+            Transaction::read_vec(vec![0xEF, 0x40, 0x18]),
+            Transaction::transaction_end(),
+        ];
+        expectations.extend(read_sfdp(0, vec![0xFF; 16]));
+        let spi = Mock::new(&expectations);
+        let mut flash = SpiNor::new(spi, MaxAttempts(3));
+
+        let err = flash.probe::<4096, 2>().unwrap_err();
+        assert!(matches!(err, Error::Sfdp));
+
+        flash.into_inner().0.done();
+    }
+
+    #[test]
+    fn test_read_jedec_id() {
+        let expectations = [
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![READ_JEDEC_ID]),
+            Transaction::read_vec(vec![0xEF, 0x40, 0x18]),
+            Transaction::transaction_end(),
+        ];
+        let spi = Mock::new(&expectations);
+        let mut flash = SpiNor::new(spi, MaxAttempts(3));
+
+        assert_eq!(flash.read_jedec_id().unwrap(), [0xEF, 0x40, 0x18]);
+
+        flash.into_inner().0.done();
+    }
+
+    #[test]
+    fn test_read() {
+        let expectations = [
+            Transaction::transaction_start(),
+            Transaction::write_vec(vec![READ, 0x01, 0x23, 0x45]),
+            Transaction::read_vec(vec![1, 2, 3]),
+            Transaction::transaction_end(),
+        ];
+        let spi = Mock::new(&expectations);
+        let mut flash = SpiNor::new(spi, MaxAttempts(3));
+
+        let mut buf = [0u8; 3];
+        flash.read(0x01_2345, &mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3]);
+
+        flash.into_inner().0.done();
+    }
+
+    #[test]
+    fn test_write_page_boundary() {
+        let mut expectations = command(vec![vec![PAGE_PROGRAM, 0x00, 0x10, 0xFE], vec![1, 2]]);
+        expectations.extend(read_status(&[STATUS_BUSY, 0]));
+        expectations.extend(command(vec![
+            vec![PAGE_PROGRAM, 0x00, 0x11, 0x00],
+            vec![3; 256],
+        ]));
+        expectations.extend(read_status(&[0]));
+        expectations.extend(command(vec![vec![PAGE_PROGRAM, 0x00, 0x12, 0x00], vec![4]]));
+        expectations.extend(read_status(&[0]));
+        let spi = Mock::new(&expectations);
+        let mut flash = SpiNor::new(spi, MaxAttempts(3));
+
+        let mut data = [[1, 2].as_slice(), &[3; 256], &[4]].concat();
+        flash.write(0x10FE, &mut data).unwrap();
+
+        flash.into_inner().0.done();
+    }
+
+    #[test]
+    fn test_erase() {
+        let mut expectations = command(vec![vec![SECTOR_ERASE, 0x00, 0x20, 0x00]]);
+        expectations.extend(read_status(&[STATUS_BUSY, STATUS_BUSY, 0]));
+        expectations.extend(command(vec![vec![CHIP_ERASE]]));
+        expectations.extend(read_status(&[0]));
+        let spi = Mock::new(&expectations);
+        let mut flash = SpiNor::new(spi, MaxAttempts(3));
+
+        flash.erase(0x2040).unwrap();
+        flash.erase_chip().unwrap();
+
+        flash.into_inner().0.done();
+    }
+
+    #[test]
+    fn test_erase_all_blocks() {
+        let mut expectations = vec![];
+        for cmd in [
+            [BLOCK_ERASE_64K, 0x00, 0x00, 0x00],
+            [BLOCK_ERASE_32K, 0x01, 0x00, 0x00],
+            [SECTOR_ERASE, 0x01, 0x80, 0x00],
+            [SECTOR_ERASE, 0x01, 0x90, 0x00],
+        ] {
+            expectations.extend(command(vec![cmd.to_vec()]));
+            expectations.extend(read_status(&[0]));
+        }
+        let spi = Mock::new(&expectations);
+        let mut flash = SpiNor::new(spi, MaxAttempts(3));
+
+        // Only the storage region is erased, not the whole chip
+        flash.erase_all(0x1_A000).unwrap();
+
+        flash.into_inner().0.done();
+    }
+
+    #[test]
+    fn test_erase_timeout() {
+        let mut expectations = command(vec![vec![SECTOR_ERASE, 0x00, 0x00, 0x00]]);
+        expectations.extend(read_status(&[STATUS_BUSY, STATUS_BUSY]));
+        let spi = Mock::new(&expectations);
+        let mut flash = SpiNor::new(spi, MaxAttempts(2));
+
+        let err = flash.erase(0).unwrap_err();
+        assert!(matches!(err, Error::Timeout));
+
+        flash.into_inner().0.done();
+    }
+}
@@ -5,12 +5,25 @@
 //! - The [`Storage`] type for managing savegames
 //! - Methods for reading, writing, and scanning savegames
 
+#[cfg(feature = "compress")]
+use crate::compress;
+#[cfg(feature = "delta")]
+use crate::delta;
+#[cfg(feature = "encrypt")]
+use crate::encrypt::{self, Opener};
+#[cfg(feature = "mac")]
+use crate::mac::{self, Key, Mac};
 use crate::{
     Slot,
     chksum::{self, Chksum},
 };
+#[cfg(feature = "encrypt")]
+use chacha20::cipher::StreamCipher;
 use core::fmt;
 
+/// The largest programming unit ([`Flash::WRITE_SIZE`]) supported by [`Storage`]
+pub const MAX_WRITE_SIZE: usize = 64;
+
 /// Trait for flash memory operations
 ///
 /// Implement this trait for your flash hardware to use with [`Storage`].
@@ -19,6 +32,38 @@ pub trait Flash {
     /// The error type for flash operations
     type Error: fmt::Debug;
 
+    /// Size of the region cleared by a single [`Flash::erase`] call in bytes
+    ///
+    /// Byte-writable memory like EEPROM keeps the default of `1`. For NOR flash
+    /// this is the sector size: slots are packed into a sector and the sector
+    /// is only erased when the first slot in it is written.
+    const ERASE_SIZE: usize = 1;
+
+    /// Programming unit of the flash in bytes
+    ///
+    /// Some flash (e.g. internal MCU flash) can only program aligned multiples
+    /// of a write unit and can't program the same unit twice between erases.
+    /// [`Storage`] pads the header, data and continuation bytes to this size, so
+    /// every write is aligned and touches each unit at most once. Must not
+    /// exceed [`MAX_WRITE_SIZE`].
+    const WRITE_SIZE: usize = 1;
+
+    /// The value erased flash reads as
+    ///
+    /// Most flash reads erased bytes as `0xFF`, some memory (e.g. certain FRAM
+    /// or RRAM configurations) reads them as `0x00`. [`Storage`] encodes slot
+    /// headers relative to this value, so blank slots are never mistaken for
+    /// written ones.
+    const ERASED: u8 = 0xFF;
+
+    /// Whether the memory needs an erase before it can be written
+    ///
+    /// Memory that overwrites bytes in place without an erase cycle (e.g. FRAM)
+    /// sets this to `false`. [`Storage`] then never calls [`Flash::erase`] while
+    /// writing savegames, and overwrites the start of a slot once to mark it as
+    /// unused directly.
+    const NEEDS_ERASE: bool = true;
+
     /// Read data from flash memory at the specified byte address
     ///
     /// # Arguments
@@ -30,26 +75,47 @@ pub trait Flash {
     /// Write data to flash memory at the specified address
     ///
     /// Note: The data parameter is mutable because some flash drivers (e.g., w25q)
-    /// require mutable access during write operations.
+    /// require mutable access during write operations. [`Storage`] writes through
+    /// [`Flash::write_slice`] instead, so its own write methods take immutable data.
     fn write(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Self::Error>;
 
+    /// Write immutable data to flash memory at the specified address
+    ///
+    /// The default implementation copies the data into a buffer of at most
+    /// [`MAX_WRITE_SIZE`] bytes for [`Flash::write`], which splits larger page
+    /// programs. Drivers that don't need mutable data pass it straight through.
+    fn write_slice(&mut self, addr: u32, data: &[u8]) -> Result<(), Self::Error> {
+        let chunk_size = MAX_WRITE_SIZE - MAX_WRITE_SIZE % Self::WRITE_SIZE;
+        let mut buf = [0u8; MAX_WRITE_SIZE];
+        for (i, chunk) in data.chunks(chunk_size).enumerate() {
+            let buf = &mut buf[..chunk.len()];
+            buf.copy_from_slice(chunk);
+            let offset = i.saturating_mul(chunk_size) as u32;
+            self.write(addr.saturating_add(offset), buf)?;
+        }
+        Ok(())
+    }
+
     /// Erase a flash sector or replace first byte to invalidate a slot
     ///
     /// For EEPROM, this typically sets the first byte to 0xFF.
     /// For NOR flash, this erases an entire sector.
     fn erase(&mut self, addr: u32) -> Result<(), Self::Error>;
 
-    /// Bulk erase multiple slots/sectors
+    /// Bulk erase the storage region
     ///
-    /// Some flash chips have optimized bulk erase operations.
-    /// The default implementation erases sectors one by one.
+    /// Only the region starting at address `0` must be erased, memory beyond it
+    /// may hold other data (e.g. firmware or assets) and has to be left intact.
+    /// Some flash chips can erase larger blocks at once, the default
+    /// implementation erases sectors of [`Flash::ERASE_SIZE`] one by one.
     ///
     /// # Arguments
     ///
-    /// * `count` - The number of slots to erase
-    fn erase_all(&mut self, count: usize) -> Result<(), Self::Error> {
-        for idx in 0..count {
-            self.erase(idx as u32)?;
+    /// * `len` - The length of the storage region in bytes, a multiple of
+    ///   [`Flash::ERASE_SIZE`]
+    fn erase_all(&mut self, len: u32) -> Result<(), Self::Error> {
+        for addr in (0..len).step_by(Self::ERASE_SIZE) {
+            self.erase(addr)?;
         }
         Ok(())
     }
@@ -64,9 +130,10 @@ pub trait Flash {
 /// # Type Parameters
 ///
 /// * `F` - The flash hardware type implementing [`Flash`]
-/// * `SLOT_SIZE` - The size of each slot in bytes: this must match your flash's
-///   underlying sector/page size
-/// * `SLOT_COUNT` - The total number of slots available
+/// * `SLOT_SIZE` - The size of each slot in bytes: for flash with an erase
+///   sector size ([`Flash::ERASE_SIZE`]) this must evenly divide the sector size
+/// * `SLOT_COUNT` - The total number of slots available: for flash with an
+///   erase sector size the storage area must span at least two whole sectors
 ///
 /// # Power-fail Safety
 ///
@@ -74,6 +141,17 @@ pub trait Flash {
 /// power failure during write leaves the previous savegame intact. The scanner
 /// follows the checksum chain to find the most recent complete savegame.
 ///
+/// Headers overwritten on byte-writable memory are invalidated by a single
+/// bit first, and the first byte of a new header is written last. A write
+/// interrupted within a byte, with any mix of old and new bits, still leaves
+/// either the previous or the new savegame. Sector erases and programming
+/// units larger than a byte are assumed to complete or not happen at all.
+///
+/// Slots that share an erase sector with a previous savegame are programmed
+/// without erasing them again. An interrupted savegame leaves programmed
+/// bytes there, so the scanner moves the next free slot to the next sector
+/// unless the rest of the sector is still erased.
+///
 /// # Wear Leveling
 ///
 /// Savegames are written sequentially with wrap-around, distributing writes
@@ -83,6 +161,139 @@ pub struct Storage<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> {
     flash: F,
     prev: Chksum,
     idx: usize,
+    /// Slot index of the latest savegame, if known
+    head: Option<usize>,
+    /// Whether the next free slot is in an odd pass over the slots
+    lap: bool,
+    #[cfg(feature = "mac")]
+    key: Option<Key>,
+    #[cfg(feature = "encrypt")]
+    encrypt: bool,
+    /// Slot index of the checkpoint of the latest savegame, the number of
+    /// delta savegames following it and the checksum of its data, if known
+    #[cfg(feature = "delta")]
+    chain: Option<(usize, usize, Option<Chksum>)>,
+    #[cfg(feature = "delta")]
+    checkpoint_interval: usize,
+}
+
+/// What the start of a slot holds, see [`Storage::scan_fast`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Probe {
+    /// Erased or never written
+    Erased,
+    /// Neither a header nor a continuation marker
+    Unknown,
+    /// A savegame header with its lap
+    Header(bool),
+    /// A continuation marker with its lap
+    Continuation(bool),
+}
+
+impl Probe {
+    /// Whether the slot was written in an odd pass over the slots
+    const fn lap(self) -> Option<bool> {
+        match self {
+            Self::Erased | Self::Unknown => None,
+            Self::Header(lap) | Self::Continuation(lap) => Some(lap),
+        }
+    }
+}
+
+/// Classification of a slot by [`Storage::scan_report`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotStatus {
+    /// Erased or never written
+    Blank,
+    /// The header of the latest savegame
+    Head,
+    /// Continuation of a valid savegame starting in an earlier slot
+    Continuation,
+    /// The header of a valid savegame older than the latest one
+    Stale,
+    /// A header failing validation, or data not written by [`Storage`]
+    Corrupt,
+    /// Continuation of no valid savegame, e.g. of an interrupted write or of a
+    /// savegame whose header was overwritten
+    Orphan,
+}
+
+/// Diagnostics about every slot, see [`Storage::scan_report`]
+#[derive(Debug, PartialEq)]
+pub struct ScanReport<const SLOT_COUNT: usize> {
+    /// The status of each slot, by slot index
+    pub slots: [SlotStatus; SLOT_COUNT],
+    /// The latest savegame, as found by [`Storage::scan`]
+    pub head: Option<Slot>,
+}
+
+impl<const SLOT_COUNT: usize> ScanReport<SLOT_COUNT> {
+    /// Count the slots with the given status
+    pub fn count(&self, status: SlotStatus) -> usize {
+        self.slots.iter().filter(|&&slot| slot == status).count()
+    }
+}
+
+/// Location of the latest savegame, to skip the full scan at boot
+///
+/// Get it with [`Storage::hint`] after a savegame was written or found, keep it
+/// somewhere that survives a reset (e.g. RTC backup registers or a no-init RAM
+/// section) and pass it to [`Storage::scan_with_hint`] on the next boot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanHint {
+    /// Slot index of the latest savegame
+    pub idx: usize,
+    /// Checksum of the latest savegame
+    pub chksum: Chksum,
+}
+
+impl ScanHint {
+    /// Size of the serialized hint in bytes
+    pub const SIZE: usize = 4 + Chksum::SIZE;
+
+    /// Serialize the hint for storing it outside of the flash
+    ///
+    /// The format is: slot index (4 bytes, big endian) + checksum (4 bytes).
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut buf = [0u8; Self::SIZE];
+        let (idx, chksum) = arrayref::mut_array_refs![&mut buf, 4, Chksum::SIZE];
+        *idx = (self.idx as u32).to_be_bytes();
+        *chksum = self.chksum.to_bytes();
+        buf
+    }
+
+    /// Deserialize a hint created with [`ScanHint::to_bytes`]
+    ///
+    /// The hint isn't validated here, a corrupted hint is rejected by
+    /// [`Storage::scan_with_hint`].
+    pub fn from_bytes(buf: [u8; Self::SIZE]) -> Self {
+        let (idx, chksum) = arrayref::array_refs![&buf, 4, Chksum::SIZE];
+        Self {
+            idx: u32::from_be_bytes(*idx) as usize,
+            chksum: Chksum::from_bytes(*chksum),
+        }
+    }
+}
+
+/// Position within the data of a savegame spanning one or more slots
+///
+/// Used to stream data into or out of the slots, skipping the header of the
+/// first slot and the continuation marker of subsequent slots.
+#[derive(Debug)]
+pub(crate) struct Cursor {
+    /// The slot the cursor is in
+    pub(crate) idx: usize,
+    /// The next flash address to access
+    addr: u32,
+    /// Space left in the slot from `addr` on
+    remaining: usize,
+    /// Data of a partially filled programming unit at `addr`, not yet written
+    pending: [u8; MAX_WRITE_SIZE],
+    /// Number of bytes in `pending`
+    pending_len: usize,
+    /// Whether the slot is in an odd pass over the slots, for the markers of
+    /// written continuation slots
+    lap: bool,
 }
 
 impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> Storage<F, SLOT_SIZE, SLOT_COUNT> {
@@ -92,16 +303,132 @@ impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> Storage<F, SLOT_
     /// for slot metadata and headers.
     pub const SPACE: u32 = SLOT_SIZE as u32 * SLOT_COUNT as u32;
 
+    /// Space taken by the slot header, padded to the programming unit
+    const HEADER_SPACE: usize = Slot::HEADER_SIZE.next_multiple_of(F::WRITE_SIZE);
+
+    /// Space taken by the continuation byte, padded to the programming unit
+    const MARKER_SPACE: usize = F::WRITE_SIZE;
+
+    /// Largest chunk that is a multiple of the programming unit
+    const CHUNK_SIZE: usize = MAX_WRITE_SIZE - MAX_WRITE_SIZE % F::WRITE_SIZE;
+
+    /// The largest savegame in bytes that fits into `slots` consecutive slots
+    const fn data_space(slots: usize) -> usize {
+        match slots {
+            0 => 0,
+            slots => {
+                (SLOT_SIZE - Self::HEADER_SPACE) + (slots - 1) * (SLOT_SIZE - Self::MARKER_SPACE)
+            }
+        }
+    }
+
     /// Create a new storage manager
     ///
     /// This is a cheap operation and does not initialize or scan the flash
     /// memory.
     pub const fn new(flash: F) -> Self {
+        // Sanity check
+        const {
+            assert!(
+                F::WRITE_SIZE <= MAX_WRITE_SIZE,
+                "Flash::WRITE_SIZE exceeds MAX_WRITE_SIZE"
+            );
+            assert!(
+                SLOT_SIZE.is_multiple_of(F::WRITE_SIZE),
+                "SLOT_SIZE must be a multiple of Flash::WRITE_SIZE"
+            );
+            assert!(
+                SLOT_SIZE > Self::HEADER_SPACE,
+                "Invalid SLOT_SIZE, Slot::HEADER_SIZE doesn't fit"
+            );
+            if F::ERASE_SIZE > 1 {
+                assert!(
+                    F::ERASE_SIZE.is_multiple_of(SLOT_SIZE),
+                    "SLOT_SIZE must evenly divide Flash::ERASE_SIZE"
+                );
+                assert!(
+                    (SLOT_SIZE * SLOT_COUNT).is_multiple_of(F::ERASE_SIZE),
+                    "Storage must consist of whole erase sectors"
+                );
+                assert!(
+                    SLOT_SIZE * SLOT_COUNT >= F::ERASE_SIZE * 2,
+                    "Storage must span at least two erase sectors"
+                );
+            }
+        }
+
         Self {
             flash,
             prev: Chksum::zero(),
             idx: 0,
+            head: None,
+            lap: false,
+            #[cfg(feature = "mac")]
+            key: None,
+            #[cfg(feature = "encrypt")]
+            encrypt: false,
+            #[cfg(feature = "delta")]
+            chain: None,
+            #[cfg(feature = "delta")]
+            checkpoint_interval: delta::CHECKPOINT_INTERVAL,
+        }
+    }
+
+    /// Create a new storage manager for authenticated savegames
+    ///
+    /// A MAC keyed with `key` is appended to every savegame written, and
+    /// savegames failing verification are ignored by [`Storage::scan`] and
+    /// [`Storage::read`]. See the [`mac`] module for details.
+    /// Savegames take [`mac::TAG_SIZE`] bytes more space and the header length
+    /// field includes the MAC.
+    #[cfg(feature = "mac")]
+    pub const fn with_key(flash: F, key: Key) -> Self {
+        let mut storage = Self::new(flash);
+        storage.key = Some(key);
+        storage
+    }
+
+    /// Create a new storage manager for encrypted savegames
+    ///
+    /// Savegames are encrypted with a key derived from `key` and authenticated,
+    /// savegames failing verification are ignored by [`Storage::scan`] and
+    /// [`Storage::read`]. See the [`encrypt`] module for details. Savegames
+    /// take [`encrypt::IV_SIZE`] bytes more space and the header length field
+    /// includes the IV.
+    #[cfg(feature = "encrypt")]
+    pub const fn with_encryption(flash: F, key: Key) -> Self {
+        let mut storage = Self::with_key(flash, key);
+        storage.encrypt = true;
+        storage
+    }
+
+    /// Key to authenticate savegames with a MAC, if they aren't encrypted
+    #[cfg(feature = "mac")]
+    fn mac_key(&self) -> Option<&Key> {
+        #[cfg(feature = "encrypt")]
+        if self.encrypt {
+            return None;
+        }
+        self.key.as_ref()
+    }
+
+    /// Key to encrypt savegames with
+    #[cfg(feature = "encrypt")]
+    pub(crate) fn encrypt_key(&self) -> Option<&Key> {
+        self.key.as_ref().filter(|_| self.encrypt)
+    }
+
+    /// Space taken by the MAC or IV of a savegame, `0` without a key
+    pub(crate) const fn overhead(&self) -> usize {
+        #[cfg(feature = "encrypt")]
+        if self.encrypt {
+            return encrypt::IV_SIZE;
         }
+        #[cfg(feature = "mac")]
+        if self.key.is_some() {
+            return mac::TAG_SIZE;
+        }
+        0
     }
 
     /// Calculate the flash memory address of a slot by its index
@@ -109,6 +436,371 @@ impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> Storage<F, SLOT_
         ((idx % SLOT_COUNT) * SLOT_SIZE) as u32
     }
 
+    /// Invalidate the header at the start of a slot before it's overwritten
+    ///
+    /// A byte interrupted while it's overwritten may end up with any mix of
+    /// old and new bits, which could leave a valid header with another
+    /// checksum that breaks the chain to the next savegame. On byte-writable
+    /// memory only the bit marking the header invalid is set first.
+    ///
+    /// Returns whether the header was invalidated.
+    fn invalidate_slot(&mut self, addr: u32) -> Result<bool, F::Error> {
+        if F::ERASE_SIZE > 1 || F::WRITE_SIZE > 1 {
+            return Ok(false);
+        }
+        let mut byte = [0u8];
+        self.flash.read(addr, &mut byte)?;
+        Self::encode_header(&mut byte);
+        if byte[0] & chksum::BYTE_MASK != 0 {
+            return Ok(false);
+        }
+        byte[0] |= chksum::BYTE_MASK;
+        Self::encode_header(&mut byte);
+        self.flash.write(addr, &mut byte)?;
+        Ok(true)
+    }
+
+    /// Erase a slot before writing to it
+    ///
+    /// Slots are written sequentially, so a slot that doesn't start an erase
+    /// sector has already been erased together with the first slot of its
+    /// sector. Memory without erase only gets the start of the slot overwritten
+    /// once, a header there is invalidated instead of erased.
+    fn erase_slot(&mut self, addr: u32) -> Result<(), F::Error> {
+        if !F::NEEDS_ERASE {
+            if !self.invalidate_slot(addr)? {
+                self.write_padded(addr, &[F::ERASED])?;
+            }
+            return Ok(());
+        }
+        self.invalidate_slot(addr)?;
+        if (addr as usize).is_multiple_of(F::ERASE_SIZE) {
+            self.flash.erase(addr)?;
+        }
+        Ok(())
+    }
+
+    /// Mark a slot as continuing the savegame of the previous slot
+    ///
+    /// Like [`Storage::erase_slot`], but the start of the slot is programmed
+    /// with [`Slot::CONTINUATION_MARKER`] and the lap of the slot.
+    fn write_marker(&mut self, addr: u32, lap: bool) -> Result<(), F::Error> {
+        if F::NEEDS_ERASE {
+            self.erase_slot(addr)?;
+        } else {
+            self.invalidate_slot(addr)?;
+        }
+        self.program_marker(addr, lap)
+    }
+
+    /// Program [`Slot::CONTINUATION_MARKER`] and a lap into the start of a slot
+    fn program_marker(&mut self, addr: u32, lap: bool) -> Result<(), F::Error> {
+        let mut marker = [Slot::CONTINUATION_MARKER | lap as u8];
+        Self::encode_header(&mut marker);
+        self.write_padded(addr, &marker)
+    }
+
+    /// Whether slot `idx` is written in an odd pass over the slots
+    ///
+    /// Slots before the next free slot are only written again after the next
+    /// wrap-around.
+    pub(crate) const fn lap_at(&self, idx: usize) -> bool {
+        self.lap ^ (idx < self.idx)
+    }
+
+    /// The [`Slot::FLAG_LAP`] of a savegame written into slot `idx`
+    pub(crate) const fn lap_flag(&self, idx: usize) -> u8 {
+        if self.lap_at(idx) { Slot::FLAG_LAP } else { 0 }
+    }
+
+    /// Move the next free slot forward to `next`, counting wrap-arounds
+    const fn move_to(&mut self, next: usize) {
+        self.lap ^= next <= self.idx;
+        self.idx = next;
+    }
+
+    /// Move the next free slot forward to `next`, past slots that can't be
+    /// written again
+    ///
+    /// Skipped slots still starting erased get a continuation marker, so
+    /// [`Storage::scan_with_hint`] and [`Storage::scan_fast`] walk past them
+    /// to the savegames behind. The slots are skipped even if that fails.
+    fn skip_to(&mut self, next: usize) -> Result<(), F::Error> {
+        let (mut idx, mut lap) = (self.idx, self.lap);
+        if idx != next {
+            self.move_to(next);
+        }
+        while idx != next {
+            if self.probe(idx)? == Probe::Erased {
+                self.program_marker(self.addr(idx), lap)?;
+            }
+            idx = idx.saturating_add(1) % SLOT_COUNT;
+            lap ^= idx == 0;
+        }
+        Ok(())
+    }
+
+    /// Encode or decode slot header bytes for the erased state of the flash
+    ///
+    /// Headers are stored XOR'ed with the inverted erased value, so erased
+    /// flash always decodes to `0xFF` and fails the validity check. Since XOR
+    /// is its own inverse, this is used for both directions.
+    fn encode_header(bytes: &mut [u8]) {
+        for byte in bytes {
+            *byte ^= !F::ERASED;
+        }
+    }
+
+    /// Write the header of a savegame to its slot
+    ///
+    /// The first byte is only valid once it's written, so the programming unit
+    /// holding it is written last. A write interrupted before never leaves a
+    /// header mixing the new and old fields.
+    pub(crate) fn write_header(&mut self, slot: &Slot) -> Result<(), F::Error> {
+        let mut bytes = slot.to_bytes();
+        Self::encode_header(&mut bytes);
+        let addr = self.addr(slot.idx);
+        let unit = F::WRITE_SIZE.min(Slot::HEADER_SIZE);
+        let (head, tail) = bytes.split_at(unit);
+        self.write_padded(addr.saturating_add(unit as u32), tail)?;
+        self.write_padded(addr, head)
+    }
+
+    /// Write data that is a multiple of the programming unit
+    fn write_aligned(&mut self, addr: u32, data: &[u8]) -> Result<(), F::Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.flash.write_slice(addr, data)
+    }
+
+    /// Write data padded to the programming unit of the flash
+    ///
+    /// The aligned part is written in chunks, a trailing partial unit is
+    /// padded with the erased state.
+    fn write_padded(&mut self, addr: u32, data: &[u8]) -> Result<(), F::Error> {
+        let aligned = data.len() - data.len() % F::WRITE_SIZE;
+        let (data, tail) = data.split_at(aligned);
+        self.write_aligned(addr, data)?;
+
+        if !tail.is_empty() {
+            let mut buf = [F::ERASED; MAX_WRITE_SIZE];
+            let buf = &mut buf[..F::WRITE_SIZE];
+            buf[..tail.len()].copy_from_slice(tail);
+            self.flash.write(addr.saturating_add(aligned as u32), buf)?;
+        }
+
+        Ok(())
+    }
+
+    /// Start a cursor at the data of the savegame in slot `idx`
+    pub(crate) const fn cursor(&self, idx: usize) -> Cursor {
+        Cursor {
+            idx,
+            addr: self.addr(idx).saturating_add(Self::HEADER_SPACE as u32),
+            remaining: SLOT_SIZE - Self::HEADER_SPACE,
+            pending: [F::ERASED; MAX_WRITE_SIZE],
+            pending_len: 0,
+            lap: false,
+        }
+    }
+
+    /// Start a cursor to write a savegame into slot `idx`
+    pub(crate) const fn write_cursor(&self, idx: usize) -> Cursor {
+        let mut cursor = self.cursor(idx);
+        cursor.lap = self.lap_at(idx);
+        cursor
+    }
+
+    /// Start a cursor at `offset` into the data of the savegame in slot `idx`
+    #[cfg(feature = "io")]
+    pub(crate) const fn cursor_at(&self, idx: usize, offset: usize) -> Cursor {
+        let mut cursor = self.cursor(idx);
+        let Some(offset) = offset.checked_sub(cursor.remaining) else {
+            cursor.addr = cursor.addr.saturating_add(offset as u32);
+            cursor.remaining -= offset;
+            return cursor;
+        };
+
+        // Skip whole continuation slots
+        let space = SLOT_SIZE - Self::MARKER_SPACE;
+        cursor.idx = cursor.idx.saturating_add(offset / space);
+        self.advance(&mut cursor);
+        cursor.addr = cursor.addr.saturating_add((offset % space) as u32);
+        cursor.remaining -= offset % space;
+        cursor
+    }
+
+    /// Move a cursor to the data of the next slot
+    const fn advance(&self, cursor: &mut Cursor) {
+        cursor.idx = cursor.idx.saturating_add(1) % SLOT_COUNT;
+        cursor.lap ^= cursor.idx == 0;
+        cursor.addr = self
+            .addr(cursor.idx)
+            .saturating_add(Self::MARKER_SPACE as u32);
+        cursor.remaining = SLOT_SIZE - Self::MARKER_SPACE;
+    }
+
+    /// Read data at a cursor, continuing into subsequent slots as needed
+    pub(crate) fn read_data(
+        &mut self,
+        cursor: &mut Cursor,
+        mut buf: &mut [u8],
+    ) -> Result<(), F::Error> {
+        while !buf.is_empty() {
+            if cursor.remaining == 0 {
+                self.advance(cursor);
+            }
+
+            let read_size = cursor.remaining.min(buf.len());
+            let (to_read, remaining) = buf.split_at_mut(read_size);
+            self.flash.read(cursor.addr, to_read)?;
+            buf = remaining;
+
+            cursor.addr = cursor.addr.saturating_add(read_size as u32);
+            cursor.remaining -= read_size;
+        }
+        Ok(())
+    }
+
+    /// Write data at a cursor, continuing into subsequent slots as needed
+    ///
+    /// Subsequent slots are erased before they are written. A trailing partial
+    /// programming unit is kept in the cursor until more data follows or the
+    /// cursor is flushed with [`Storage::flush`].
+    pub(crate) fn write_data(
+        &mut self,
+        cursor: &mut Cursor,
+        mut data: &[u8],
+    ) -> Result<(), F::Error> {
+        while !data.is_empty() {
+            // Slots end on a unit boundary, so the pending unit was written already
+            if cursor.remaining == cursor.pending_len {
+                self.advance(cursor);
+                self.write_marker(cursor.addr - Self::MARKER_SPACE as u32, cursor.lap)?;
+            }
+
+            let write_size = (cursor.remaining - cursor.pending_len).min(data.len());
+            let (mut chunk, remaining) = data.split_at(write_size);
+            data = remaining;
+
+            // Complete a pending unit first
+            if cursor.pending_len > 0 {
+                let fill = (F::WRITE_SIZE - cursor.pending_len).min(chunk.len());
+                let (head, tail) = chunk.split_at(fill);
+                cursor.pending[cursor.pending_len..][..fill].copy_from_slice(head);
+                cursor.pending_len += fill;
+                chunk = tail;
+
+                if cursor.pending_len < F::WRITE_SIZE {
+                    continue;
+                }
+                self.flush(cursor)?;
+            }
+
+            let aligned = chunk.len() - chunk.len() % F::WRITE_SIZE;
+            let (chunk, tail) = chunk.split_at(aligned);
+            self.write_aligned(cursor.addr, chunk)?;
+            cursor.addr = cursor.addr.saturating_add(aligned as u32);
+            cursor.remaining -= aligned;
+
+            cursor.pending[..tail.len()].copy_from_slice(tail);
+            cursor.pending_len = tail.len();
+        }
+        Ok(())
+    }
+
+    /// Write the pending partial programming unit of a cursor, padded with the
+    /// erased state
+    pub(crate) fn flush(&mut self, cursor: &mut Cursor) -> Result<(), F::Error> {
+        if cursor.pending_len > 0 {
+            let buf = &mut cursor.pending[..F::WRITE_SIZE];
+            buf[cursor.pending_len..].fill(F::ERASED);
+            self.flash.write(cursor.addr, buf)?;
+            cursor.addr = cursor.addr.saturating_add(F::WRITE_SIZE as u32);
+            cursor.remaining -= F::WRITE_SIZE;
+            cursor.pending_len = 0;
+        }
+        Ok(())
+    }
+
+    /// Read `len` bytes at a cursor in chunks, without a buffer for all of them
+    #[cfg(any(feature = "mac", feature = "delta"))]
+    fn read_chunks(
+        &mut self,
+        cursor: &mut Cursor,
+        len: usize,
+        mut f: impl FnMut(&mut [u8]),
+    ) -> Result<(), F::Error> {
+        let mut buf = [0u8; MAX_WRITE_SIZE];
+        let mut remaining = len;
+        while remaining > 0 {
+            let buf = &mut buf[..remaining.min(MAX_WRITE_SIZE)];
+            self.read_data(cursor, buf)?;
+            f(buf);
+            remaining -= buf.len();
+        }
+        Ok(())
+    }
+
+    /// Verify a savegame, always `true` without the `mac` feature
+    #[cfg(not(feature = "mac"))]
+    pub(crate) fn authenticate(&mut self, _slot: &Slot) -> Result<bool, F::Error> {
+        Ok(true)
+    }
+
+    /// Verify the MAC or IV of a savegame, always `true` without a key
+    #[cfg(feature = "mac")]
+    pub(crate) fn authenticate(&mut self, slot: &Slot) -> Result<bool, F::Error> {
+        if self.overhead() == 0 {
+            return Ok(true);
+        }
+        self.stream_payload(slot, |_| {})
+    }
+
+    /// Pass the stored data of a savegame to `f` in chunks and verify it
+    ///
+    /// Returns `false` if the savegame fails verification, after all of its
+    /// data was passed to `f`.
+    #[cfg(any(feature = "mac", feature = "delta"))]
+    fn stream_payload(
+        &mut self,
+        slot: &Slot,
+        mut f: impl FnMut(&mut [u8]),
+    ) -> Result<bool, F::Error> {
+        let Some(len) = (slot.len as usize).checked_sub(self.overhead()) else {
+            return Ok(false);
+        };
+        let mut cursor = self.cursor(slot.idx);
+
+        #[cfg(feature = "encrypt")]
+        if let Some(key) = self.encrypt_key().cloned() {
+            let mut iv = [0u8; encrypt::IV_SIZE];
+            self.read_data(&mut cursor, &mut iv)?;
+            let mut opener = Opener::new(&key, slot.prev, slot.flags, len, iv);
+            self.read_chunks(&mut cursor, len, |buf| {
+                opener.decrypt(buf);
+                f(buf);
+            })?;
+            return Ok(opener.verify());
+        }
+
+        #[cfg(feature = "mac")]
+        if let Some(key) = self.mac_key() {
+            let mut mac = Mac::new(key, &slot.to_bytes());
+            self.read_chunks(&mut cursor, len, |buf| {
+                mac.update(buf);
+                f(buf);
+            })?;
+            let mut tag = [0u8; mac::TAG_SIZE];
+            self.read_data(&mut cursor, &mut tag)?;
+            return Ok(mac.verify(&tag));
+        }
+
+        self.read_chunks(&mut cursor, len, |buf| f(buf))?;
+        Ok(true)
+    }
+
     /// Probe a single slot for a valid savegame header
     fn scan_slot(&mut self, idx: usize) -> Result<Option<Slot>, F::Error> {
         let mut buf = [0u8; Slot::HEADER_SIZE];
@@ -117,7 +809,10 @@ impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> Storage<F, SLOT_
         // Read first byte for sanity check to allow early skip
         let addr = self.addr(idx);
         self.flash.read(addr, head)?;
+        Self::encode_header(head);
 
+        // Continuation markers and erased slots never start a header, so the
+        // payload behind a marker isn't parsed as one
         if head[0] & chksum::BYTE_MASK != 0 {
             return Ok(None);
         }
@@ -125,11 +820,18 @@ impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> Storage<F, SLOT_
         // Read the rest of the header
         let addr = addr.saturating_add(1);
         self.flash.read(addr, tail)?;
+        Self::encode_header(tail);
 
-        // Parse and validate slot
+        // Parse and validate slot, a corrupted length could make verifying
+        // the MAC read around all slots many times
         let slot = Slot::from_bytes(idx, buf);
-        let slot = slot.is_valid().then_some(slot);
-        Ok(slot)
+        if !slot.is_valid()
+            || slot.len as usize > Self::data_space(SLOT_COUNT)
+            || !self.authenticate(&slot)?
+        {
+            return Ok(None);
+        }
+        Ok(Some(slot))
     }
 
     /// Scan all slots for the most recent valid savegame
@@ -155,655 +857,3622 @@ impl<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize> Storage<F, SLOT_
         }
 
         if let Some(current) = &current {
-            self.idx = current.next_slot::<SLOT_SIZE, SLOT_COUNT>();
-            self.prev = current.chksum;
+            self.found(current)?;
         }
 
         Ok(current)
     }
 
-    /// Mark a slot as unused (by partially or fully erasing it)
+    /// Update the internal state to continue after the latest savegame
+    fn found(&mut self, slot: &Slot) -> Result<(), F::Error> {
+        self.idx = slot.idx;
+        self.lap = slot.flags & Slot::FLAG_LAP != 0;
+        self.move_to(slot.next_padded_slot::<SLOT_SIZE, SLOT_COUNT>(F::WRITE_SIZE));
+        self.prev = slot.chksum;
+        self.head = Some(slot.idx);
+        #[cfg(feature = "delta")]
+        {
+            self.chain = self
+                .find_checkpoint(slot)?
+                .map(|(checkpoint, deltas)| (checkpoint, deltas, None));
+        }
+        self.skip_programmed()
+    }
+
+    /// Move the next free slot to the next erase sector, if the rest of its
+    /// sector isn't erased
     ///
-    /// This may not securely erase all data (depending on the flash chip), but
-    /// prevents the slot from being detected as a valid savegame.
-    pub fn erase(&mut self, idx: usize) -> Result<(), F::Error> {
-        self.flash.erase(self.addr(idx))?;
+    /// Slots that don't start a sector are programmed without erasing them.
+    /// Programming bytes left by an interrupted savegame again would store a
+    /// mix of the old and new data under a valid header.
+    fn skip_programmed(&mut self) -> Result<(), F::Error> {
+        if !F::NEEDS_ERASE || F::ERASE_SIZE <= SLOT_SIZE {
+            return Ok(());
+        }
+        let start = self.addr(self.idx) as usize;
+        let end = start
+            .next_multiple_of(F::ERASE_SIZE)
+            .min(SLOT_SIZE * SLOT_COUNT);
+
+        let mut buf = [0u8; MAX_WRITE_SIZE];
+        let mut addr = start;
+        while addr < end {
+            let buf = &mut buf[..(end - addr).min(MAX_WRITE_SIZE)];
+            self.flash.read(addr as u32, buf)?;
+            if buf.iter().any(|&byte| byte != F::ERASED) {
+                return self.skip_to(end / SLOT_SIZE % SLOT_COUNT);
+            }
+            addr += buf.len();
+        }
         Ok(())
     }
 
-    /// Mark all slots as unused
+    /// The location of the latest savegame, if known
     ///
-    /// This may not securely erase data (depending on the flash chip), but
-    /// prevents them from being detected as valid savegames.
-    ///
-    /// On some flash chips, this may be optimized to a bulk erase operation.
-    pub fn erase_all(&mut self) -> Result<(), F::Error> {
-        self.idx = 0;
-        self.prev = Chksum::zero();
-        self.flash.erase_all(SLOT_COUNT)
+    /// Available after [`Storage::scan`] found a savegame or a savegame was
+    /// appended. See [`ScanHint`].
+    pub const fn hint(&self) -> Option<ScanHint> {
+        match self.head {
+            Some(idx) => Some(ScanHint {
+                idx,
+                chksum: self.prev,
+            }),
+            None => None,
+        }
     }
 
-    /// Read a savegame from a specific slot index
+    /// Find the most recent valid savegame, using a hint from a previous boot
     ///
-    /// The slot index must point to the first slot of the savegame. This method reads
-    /// the header to determine the savegame length. If the buffer is not large enough
-    /// to hold the entire savegame, `Ok(None)` is returned. The savegame may span
-    /// multiple slots.
-    pub fn read<'a>(
-        &mut self,
-        mut idx: usize,
-        buf: &'a mut [u8],
-    ) -> Result<Option<&'a mut [u8]>, F::Error> {
-        let mut addr = self.addr(idx);
-        let mut slot = [0u8; Slot::HEADER_SIZE];
-        self.flash.read(addr, &mut slot)?;
-        addr = addr.saturating_add(Slot::HEADER_SIZE as u32);
-        let slot = Slot::from_bytes(idx, slot);
-
-        let Some(data) = buf.get_mut(..slot.len as usize) else {
-            return Ok(None);
+    /// Instead of scanning all slots only the savegame the hint points to and
+    /// the slots following it are checked: the savegame must be valid with the
+    /// same checksum, and the next header must not be a newer savegame.
+    /// Otherwise the hint is stale and this falls back to [`Storage::scan`].
+    ///
+    /// This relies on savegames being appended at the next free slot, so a
+    /// newer savegame follows the one the hint points to. Only continuation
+    /// slots, such as those of an older savegame or skipped after an
+    /// unfinished one, may come in between and are walked past up to the next
+    /// header or erased slot.
+    pub fn scan_with_hint(&mut self, hint: ScanHint) -> Result<Option<Slot>, F::Error> {
+        let slot = if hint.idx < SLOT_COUNT {
+            self.scan_slot(hint.idx)?
+        } else {
+            None
         };
-        let mut buf = &mut *data;
-        let mut remaining_space = SLOT_SIZE - Slot::HEADER_SIZE;
-        while !buf.is_empty() {
-            let read_size = remaining_space.min(buf.len());
-            let (to_read, remaining) = buf.split_at_mut(read_size);
-            self.flash.read(addr, to_read)?;
-            buf = remaining;
-
-            idx = idx.saturating_add(1) % SLOT_COUNT;
-            addr = self.addr(idx).saturating_add(1);
-            remaining_space = SLOT_SIZE - 1;
+        if let Some(slot) = slot.filter(|slot| slot.chksum == hint.chksum)
+            && !self.newer_follows(&slot)?
+        {
+            self.found(&slot)?;
+            return Ok(Some(slot));
         }
-
-        Ok(Some(data))
+        self.scan()
     }
 
-    /// Read a static-sized savegame directly from a single slot
+    /// Check if a newer savegame follows `slot`
     ///
-    /// This is a more lightweight read operation for fixed-size data that fits
-    /// within a single slot (excluding the header). The size must not exceed
-    /// `SLOT_SIZE - Slot::HEADER_SIZE`. The embedded length field is ignored.
-    pub fn read_static<const SIZE: usize>(
-        &mut self,
-        idx: usize,
-        buf: &mut [u8; SIZE],
-    ) -> Result<(), F::Error> {
-        // Sanity check
-        const {
-            let space_available = SLOT_SIZE
-                .checked_sub(Slot::HEADER_SIZE)
-                .expect("Invalid SLOT_SIZE, Slot::HEADER_SIZE doesn't fit");
-            assert!(SIZE <= space_available);
+    /// Walks past continuation slots and slots skipped after an unfinished
+    /// savegame, up to the next erased slot or header.
+    fn newer_follows(&mut self, slot: &Slot) -> Result<bool, F::Error> {
+        let mut idx = slot.next_padded_slot::<SLOT_SIZE, SLOT_COUNT>(F::WRITE_SIZE);
+        while idx != slot.idx {
+            match self.probe(idx)? {
+                Probe::Erased => break,
+                Probe::Header(_) => {
+                    if let Some(next) = self.scan_slot(idx)? {
+                        return Ok(next.is_update_to(slot));
+                    }
+                }
+                Probe::Continuation(_) | Probe::Unknown => (),
+            }
+            idx = (idx + 1) % SLOT_COUNT;
         }
-
-        // Calculate address behind slot header
-        let addr = self.addr(idx).saturating_add(Slot::HEADER_SIZE as u32);
-        // Read data directly into the buffer in one go
-        self.flash.read(addr, buf)?;
-
-        Ok(())
+        Ok(false)
     }
 
-    /// Write a savegame starting at a specific slot index
+    /// Find the most recent valid savegame with a logarithmic number of reads
     ///
-    /// If the data doesn't fit in a single slot, this method automatically continues
-    /// to subsequent slots, erasing them as needed. Returns the next free slot index
-    /// and the checksum of the savegame that was just written.
+    /// Every slot records whether it was written in an odd pass over the
+    /// slots, see [`Slot::FLAG_LAP`]. Since savegames are appended
+    /// sequentially, the slots written since the last wrap-around come first
+    /// and the end of the latest savegame is found by binary search. From
+    /// there only the slots of the latest savegame are read back to its
+    /// header.
+    ///
+    /// Falls back to [`Storage::scan`] if the latest savegame is interrupted
+    /// or fails verification. Slots erased with [`Storage::erase`] or savegames
+    /// written out of order with [`Storage::write`] may hide newer savegames,
+    /// use [`Storage::scan`] then. With delta savegames the chain is still
+    /// followed back to its checkpoint.
+    pub fn scan_fast(&mut self) -> Result<Option<Slot>, F::Error> {
+        let end = match self.probe(0)? {
+            // Find the first slot not written in the same pass as the first slot
+            Probe::Header(lap) | Probe::Continuation(lap) => {
+                self.partition(|probe| probe.lap() == Some(lap))?
+            }
+            // Either empty, or the latest savegame ends before the wrap-around
+            Probe::Erased | Probe::Unknown => {
+                if self.partition(|probe| probe == Probe::Erased)? == SLOT_COUNT {
+                    return Ok(None);
+                }
+                SLOT_COUNT
+            }
+        };
+
+        // Walk back to the header of the latest savegame
+        let mut written = false;
+        for offset in 1..=SLOT_COUNT {
+            let idx = (end + SLOT_COUNT - offset) % SLOT_COUNT;
+            match self.probe(idx)? {
+                Probe::Header(_) => {
+                    let Some(slot) = self.scan_slot(idx)? else {
+                        break;
+                    };
+                    self.found(&slot)?;
+                    return Ok(Some(slot));
+                }
+                Probe::Continuation(_) => written = true,
+                // Slots behind the latest savegame may be erased in advance
+                Probe::Erased if !written => (),
+                Probe::Erased | Probe::Unknown => break,
+            }
+        }
+        self.scan()
+    }
+
+    /// Find the first slot from index 1 on that doesn't match `pred`
+    ///
+    /// The slots matching `pred` must come first, returns `SLOT_COUNT` if all
+    /// of them match.
+    fn partition(&mut self, mut pred: impl FnMut(Probe) -> bool) -> Result<usize, F::Error> {
+        let (mut low, mut high) = (1, SLOT_COUNT);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if pred(self.probe(mid)?) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(low)
+    }
+
+    /// Check what the start of a slot holds, without validating it
+    fn probe(&mut self, idx: usize) -> Result<Probe, F::Error> {
+        let addr = self.addr(idx);
+        let mut byte = [0u8];
+        self.flash.read(addr, &mut byte)?;
+        Self::encode_header(&mut byte);
+
+        if byte[0] & chksum::BYTE_MASK == 0 {
+            // The flags are the first byte of the length field
+            self.flash
+                .read(addr.saturating_add(Chksum::SIZE as u32), &mut byte)?;
+            Self::encode_header(&mut byte);
+            return Ok(Probe::Header(byte[0] & Slot::FLAG_LAP != 0));
+        }
+        if byte[0] & !1 == Slot::CONTINUATION_MARKER {
+            return Ok(Probe::Continuation(byte[0] & 1 != 0));
+        }
+        if byte[0] == u8::MAX {
+            return Ok(Probe::Erased);
+        }
+        Ok(Probe::Unknown)
+    }
+
+    /// Classify every slot, for diagnostics about missing savegames
+    ///
+    /// Reads the start of every slot like [`Storage::scan`] and reports the
+    /// latest savegame it would find, without changing the internal state.
+    /// Continuation slots are attributed to the valid savegames they belong
+    /// to, the others are reported as [`SlotStatus::Orphan`].
+    pub fn scan_report(&mut self) -> Result<ScanReport<SLOT_COUNT>, F::Error> {
+        let mut slots = [SlotStatus::Blank; SLOT_COUNT];
+        let mut head: Option<Slot> = None;
+
+        for (idx, status) in slots.iter_mut().enumerate() {
+            *status = match self.probe(idx)? {
+                Probe::Erased => SlotStatus::Blank,
+                Probe::Unknown => SlotStatus::Corrupt,
+                Probe::Continuation(_) => SlotStatus::Orphan,
+                Probe::Header(_) => match self.scan_slot(idx)? {
+                    Some(slot) => {
+                        if head.as_ref().is_none_or(|head| slot.is_update_to(head)) {
+                            head = Some(slot);
+                        }
+                        SlotStatus::Stale
+                    }
+                    None => SlotStatus::Corrupt,
+                },
+            };
+        }
+
+        for idx in 0..SLOT_COUNT {
+            if slots[idx] != SlotStatus::Stale {
+                continue;
+            }
+            let slot = self.read_header(idx)?;
+            let next = slot.next_padded_slot::<SLOT_SIZE, SLOT_COUNT>(F::WRITE_SIZE);
+            let mut cont = (idx + 1) % SLOT_COUNT;
+            while cont != next && slots[cont] == SlotStatus::Orphan {
+                slots[cont] = SlotStatus::Continuation;
+                cont = (cont + 1) % SLOT_COUNT;
+            }
+        }
+        if let Some(head) = &head {
+            slots[head.idx] = SlotStatus::Head;
+        }
+
+        Ok(ScanReport { slots, head })
+    }
+
+    /// Mark a slot as unused (by partially or fully erasing it)
+    ///
+    /// This may not securely erase all data (depending on the flash chip), but
+    /// prevents the slot from being detected as a valid savegame. On flash with
+    /// an erase sector size this erases all slots sharing the sector. Use
+    /// [`Storage::secure_erase`] to destroy the savegame data as well.
+    pub fn erase(&mut self, idx: usize) -> Result<(), F::Error> {
+        // The erased slot may be part of the delta chain
+        #[cfg(feature = "delta")]
+        {
+            self.chain = None;
+        }
+        self.flash.erase(self.addr(idx))?;
+        Ok(())
+    }
+
+    /// Mark all slots as unused
+    ///
+    /// This may not securely erase data (depending on the flash chip), but
+    /// prevents them from being detected as valid savegames. Use
+    /// [`Storage::secure_erase_all`] to destroy the savegame data as well.
+    ///
+    /// On flash with an erase sector size, all sectors of the storage area are
+    /// erased with [`Flash::erase_all`], which may use larger block erases.
+    /// Memory outside of the storage area is never touched.
+    pub fn erase_all(&mut self) -> Result<(), F::Error> {
+        self.reset();
+        if F::ERASE_SIZE > 1 {
+            self.flash.erase_all(Self::SPACE)
+        } else {
+            // Invalidating the start of each slot is enough on byte-writable memory
+            for idx in 0..SLOT_COUNT {
+                self.erase_slot(self.addr(idx))?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Overwrite a whole slot, so none of its bytes remain
+    ///
+    /// On flash with an erase sector size the sector containing the slot is
+    /// erased. Byte-writable memory is overwritten with the erased value in
+    /// chunks, so EEPROM spends one write cycle per page instead of per byte.
+    fn wipe_slot(&mut self, addr: u32) -> Result<(), F::Error> {
+        if F::ERASE_SIZE > 1 {
+            self.flash.erase(addr - addr % F::ERASE_SIZE as u32)?;
+        } else {
+            let mut buf = [F::ERASED; MAX_WRITE_SIZE];
+            for offset in (0..SLOT_SIZE).step_by(Self::CHUNK_SIZE) {
+                let len = Self::CHUNK_SIZE.min(SLOT_SIZE - offset);
+                self.flash
+                    .write(addr.saturating_add(offset as u32), &mut buf[..len])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Destroy a savegame, including its data
+    ///
+    /// Unlike [`Storage::erase`], every byte of the slot and of the continuation
+    /// slots of a savegame spanning multiple slots is overwritten, found by
+    /// their markers even if the header is corrupt. On flash with an erase
+    /// sector size the sectors containing these slots are fully erased.
+    ///
+    /// Returns `false` without erasing anything if these sectors hold slots
+    /// of other savegames, which would be destroyed as well. Use
+    /// [`Storage::secure_erase_all`] to destroy them together.
+    pub fn secure_erase(&mut self, idx: usize) -> Result<bool, F::Error> {
+        let mut used_slots = match self.scan_slot(idx % SLOT_COUNT)? {
+            Some(slot) => slot
+                .padded_bytes(SLOT_SIZE, F::WRITE_SIZE)
+                .div_ceil(SLOT_SIZE)
+                .min(SLOT_COUNT),
+            None => 1,
+        };
+        // The header may be corrupt, the continuation slots still have markers
+        let mut next = 1;
+        while next < SLOT_COUNT
+            && matches!(
+                self.probe(idx.saturating_add(next) % SLOT_COUNT)?,
+                Probe::Continuation(_)
+            )
+        {
+            next += 1;
+        }
+        used_slots = used_slots.max(next);
+
+        // Other slots sharing the sectors must be erased already
+        if F::ERASE_SIZE > SLOT_SIZE {
+            let sector_slots = F::ERASE_SIZE / SLOT_SIZE;
+            for n in 0..used_slots {
+                let first = (idx.saturating_add(n) % SLOT_COUNT) / sector_slots * sector_slots;
+                for other in first..(first + sector_slots).min(SLOT_COUNT) {
+                    let own = (other + SLOT_COUNT - idx % SLOT_COUNT) % SLOT_COUNT < used_slots;
+                    if !own && self.probe(other)? != Probe::Erased {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+
+        #[cfg(feature = "delta")]
+        {
+            self.chain = None;
+        }
+        for n in 0..used_slots {
+            let addr = self.addr(idx.saturating_add(n));
+            // Slots sharing a sector with the previous slot are already erased
+            if n == 0 || (addr as usize).is_multiple_of(F::ERASE_SIZE) {
+                self.wipe_slot(addr)?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Destroy all savegames, including their data
+    ///
+    /// Unlike [`Storage::erase_all`], every byte of the storage area is
+    /// overwritten on byte-writable memory. On flash with an erase sector size
+    /// this is the same as [`Storage::erase_all`], which fully erases all
+    /// sectors of the storage area.
+    pub fn secure_erase_all(&mut self) -> Result<(), F::Error> {
+        if F::ERASE_SIZE > 1 {
+            return self.erase_all();
+        }
+
+        self.reset();
+        for idx in 0..SLOT_COUNT {
+            self.wipe_slot(self.addr(idx))?;
+        }
+        Ok(())
+    }
+
+    /// Read a savegame from a specific slot index
+    ///
+    /// The slot index must point to the first slot of the savegame. This method reads
+    /// the header to determine the savegame length. If the buffer is not large enough
+    /// to hold the entire savegame, `Ok(None)` is returned. The savegame may span
+    /// multiple slots.
+    ///
+    /// For a storage created with [`Storage::with_key`], `Ok(None)` is also
+    /// returned if the savegame fails verification.
+    ///
+    /// Compressed savegames are decompressed, see the `compress` module for
+    /// the buffer size needed. Delta savegames are reconstructed from their
+    /// checkpoint, the buffer must hold the largest savegame since then. See
+    /// the `delta` module for details. Without the respective feature
+    /// `Ok(None)` is returned for them.
+    pub fn read<'a>(
+        &mut self,
+        idx: usize,
+        buf: &'a mut [u8],
+    ) -> Result<Option<&'a mut [u8]>, F::Error> {
+        let slot = self.read_header(idx % SLOT_COUNT)?;
+
+        let Some(len) = (slot.len as usize).checked_sub(self.overhead()) else {
+            return Ok(None);
+        };
+
+        #[cfg(feature = "compress")]
+        if slot.encoding() == Slot::FLAG_COMPRESSED {
+            // Read the compressed data into the end of the buffer
+            let Some(offset) = buf.len().checked_sub(len) else {
+                return Ok(None);
+            };
+            let (out, data) = buf.split_at_mut(offset);
+            if !self.read_payload(&slot, data)? {
+                return Ok(None);
+            }
+            return Ok(compress::decompress(data, out));
+        }
+
+        #[cfg(feature = "delta")]
+        if slot.encoding() == Slot::FLAG_DELTA {
+            return self.read_delta(slot, buf);
+        }
+
+        if slot.encoding() != 0 {
+            return Ok(None);
+        }
+        let Some(data) = buf.get_mut(..len) else {
+            return Ok(None);
+        };
+        if !self.read_payload(&slot, data)? {
+            return Ok(None);
+        }
+        Ok(Some(data))
+    }
+
+    /// Read the header of the savegame in slot `idx`, without validating it
+    pub(crate) fn read_header(&mut self, idx: usize) -> Result<Slot, F::Error> {
+        let mut slot = [0u8; Slot::HEADER_SIZE];
+        self.flash.read(self.addr(idx), &mut slot)?;
+        Self::encode_header(&mut slot);
+        Ok(Slot::from_bytes(idx, slot))
+    }
+
+    /// Read the stored data of a savegame and verify it
+    ///
+    /// `data` must have the length of the savegame without the MAC or IV.
+    /// Returns `false` if the savegame fails verification.
+    fn read_payload(&mut self, slot: &Slot, data: &mut [u8]) -> Result<bool, F::Error> {
+        let mut cursor = self.cursor(slot.idx);
+
+        #[cfg(feature = "encrypt")]
+        if let Some(key) = self.encrypt_key().cloned() {
+            let mut iv = [0u8; encrypt::IV_SIZE];
+            self.read_data(&mut cursor, &mut iv)?;
+            self.read_data(&mut cursor, data)?;
+            let mut opener = Opener::new(&key, slot.prev, slot.flags, data.len(), iv);
+            opener.decrypt(data);
+            if !opener.verify() {
+                // Don't hand out unauthenticated plaintext
+                data.fill(0);
+                return Ok(false);
+            }
+            return Ok(true);
+        }
+
+        self.read_data(&mut cursor, data)?;
+
+        #[cfg(feature = "mac")]
+        if let Some(key) = self.mac_key() {
+            let mut mac = Mac::new(key, &slot.to_bytes());
+            mac.update(data);
+            let mut tag = [0u8; mac::TAG_SIZE];
+            self.read_data(&mut cursor, &mut tag)?;
+            return Ok(mac.verify(&tag));
+        }
+
+        Ok(true)
+    }
+
+    /// Apply the patch of a delta savegame to `buf`, returns the patch header
+    #[cfg(feature = "delta")]
+    fn apply_patch(
+        &mut self,
+        slot: &Slot,
+        buf: &mut [u8],
+    ) -> Result<Option<delta::Header>, F::Error> {
+        let mut patch = delta::Patch::new(buf);
+        let verified = self.stream_payload(slot, |chunk| patch.update(chunk))?;
+        Ok(patch.finish().filter(|_| verified))
+    }
+
+    /// Reconstruct a delta savegame by following the chain back to its checkpoint
+    #[cfg(feature = "delta")]
+    fn read_delta<'a>(
+        &mut self,
+        head: Slot,
+        buf: &'a mut [u8],
+    ) -> Result<Option<&'a mut [u8]>, F::Error> {
+        // The patches and the checkpoint are XOR'ed together in any order
+        buf.fill(0);
+        let Some(header) = self.apply_patch(&head, buf)? else {
+            buf.fill(0);
+            return Ok(None);
+        };
+
+        let mut slot = head;
+        for _ in 0..SLOT_COUNT {
+            let Some(prev) = self.find_prev(&slot)? else {
+                break;
+            };
+            match prev.encoding() {
+                0 => {
+                    let mut offset = 0;
+                    let mut fits = true;
+                    let verified = self.stream_payload(&prev, |chunk| {
+                        fits &= delta::xor_at(buf, offset, chunk);
+                        offset += chunk.len();
+                    })?;
+                    let valid = buf
+                        .get(..header.len)
+                        .is_some_and(|data| Chksum::hash(Chksum::zero(), data) == header.chksum);
+                    if verified && fits && valid {
+                        return Ok(Some(&mut buf[..header.len]));
+                    }
+                    break;
+                }
+                Slot::FLAG_DELTA => {
+                    if self.apply_patch(&prev, buf)?.is_none() {
+                        break;
+                    }
+                }
+                _ => break,
+            }
+            slot = prev;
+        }
+
+        // Don't hand out partially reconstructed data
+        buf.fill(0);
+        Ok(None)
+    }
+
+    /// Find the savegame a savegame is an update to
+    ///
+    /// Savegames are written one after another, so this walks back from the
+    /// slot instead of scanning them all.
+    #[cfg(feature = "delta")]
+    fn find_prev(&mut self, slot: &Slot) -> Result<Option<Slot>, F::Error> {
+        for n in 1..SLOT_COUNT {
+            let idx = (slot.idx + SLOT_COUNT - n) % SLOT_COUNT;
+            if let Some(prev) = self.scan_slot(idx)?
+                && slot.is_update_to(&prev)
+            {
+                return Ok(Some(prev));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Checksum of the data of the latest savegame, `None` if it can't be
+    /// verified
+    #[cfg(feature = "delta")]
+    fn head_chksum(&mut self) -> Result<Option<Chksum>, F::Error> {
+        let Some(head) = self.head else {
+            return Ok(None);
+        };
+        let Some(slot) = self.scan_slot(head)? else {
+            return Ok(None);
+        };
+        match slot.encoding() {
+            0 => {
+                let mut hasher = chksum::Hasher::new(Chksum::zero());
+                let verified = self.stream_payload(&slot, |chunk| hasher.update(chunk))?;
+                Ok(verified.then(|| hasher.finish()))
+            }
+            Slot::FLAG_DELTA => {
+                // The checksum of the reconstructed data leads the patch
+                let mut patch = [0u8; delta::HEADER_SIZE];
+                let mut offset = 0;
+                let verified = self.stream_payload(&slot, |chunk| {
+                    let take = chunk.len().min(patch.len() - offset);
+                    patch[offset..][..take].copy_from_slice(&chunk[..take]);
+                    offset += take;
+                })?;
+                let [.., a, b, c, d] = patch;
+                let valid = verified && offset == patch.len();
+                Ok(valid.then(|| Chksum::from_bytes([a, b, c, d])))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Find the checkpoint of a savegame and the number of delta savegames
+    /// following it, `None` if the chain is broken
+    #[cfg(feature = "delta")]
+    fn find_checkpoint(&mut self, head: &Slot) -> Result<Option<(usize, usize)>, F::Error> {
+        match head.encoding() {
+            0 => return Ok(Some((head.idx, 0))),
+            Slot::FLAG_DELTA => (),
+            _ => return Ok(None),
+        }
+
+        let mut deltas = 1;
+        let mut next = self.find_prev(head)?;
+        while let Some(slot) = next {
+            match slot.encoding() {
+                0 => return Ok(Some((slot.idx, deltas))),
+                Slot::FLAG_DELTA if deltas < SLOT_COUNT => {
+                    deltas += 1;
+                    next = self.find_prev(&slot)?;
+                }
+                _ => break,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Number of slots taken by a savegame with `len` bytes of data
+    #[cfg(feature = "delta")]
+    fn used_slots(&self, len: usize) -> usize {
+        let slot = Slot {
+            idx: 0,
+            chksum: Chksum::zero(),
+            len: len.saturating_add(self.overhead()) as u32,
+            prev: Chksum::zero(),
+            flags: 0,
+        };
+        slot.padded_bytes(SLOT_SIZE, F::WRITE_SIZE)
+            .div_ceil(SLOT_SIZE)
+    }
+
+    /// Check if the chain starting at `checkpoint` stays intact while writing
+    /// a patch of `patch` bytes and a checkpoint of `len` bytes after it
+    #[cfg(feature = "delta")]
+    fn chain_fits(&self, checkpoint: usize, patch: usize, len: usize) -> bool {
+        let chain = match (self.idx + SLOT_COUNT - checkpoint) % SLOT_COUNT {
+            0 => SLOT_COUNT,
+            chain => chain,
+        };
+        // Erasing a sector clears the slots behind the written ones as well
+        let slack = F::ERASE_SIZE.div_ceil(SLOT_SIZE) - 1;
+        chain + self.used_slots(patch) + self.used_slots(len) + slack <= SLOT_COUNT
+    }
+
+    /// Read a static-sized savegame directly from a single slot
+    ///
+    /// This is a more lightweight read operation for fixed-size data that fits
+    /// within a single slot (excluding the header). The size must not exceed
+    /// `SLOT_SIZE - Slot::HEADER_SIZE`. The embedded length field is ignored
+    /// and the data isn't verified.
+    ///
+    /// With [`Storage::with_key`] or [`Storage::with_encryption`] this reads,
+    /// verifies and decrypts the savegame like [`Storage::read`] instead, and
+    /// zeroes `buf` if it fails verification.
+    pub fn read_static<const SIZE: usize>(
+        &mut self,
+        idx: usize,
+        buf: &mut [u8; SIZE],
+    ) -> Result<(), F::Error> {
+        // Sanity check
+        const {
+            let space_available = SLOT_SIZE
+                .checked_sub(Self::HEADER_SPACE)
+                .expect("Invalid SLOT_SIZE, Slot::HEADER_SIZE doesn't fit");
+            assert!(SIZE <= space_available);
+        }
+
+        if self.overhead() > 0 {
+            if self.read(idx, buf)?.is_none() {
+                buf.fill(0);
+            }
+            return Ok(());
+        }
+
+        // Calculate address behind slot header
+        let addr = self.addr(idx).saturating_add(Self::HEADER_SPACE as u32);
+        // Read data directly into the buffer in one go
+        self.flash.read(addr, buf)?;
+
+        Ok(())
+    }
+
+    /// Write a savegame starting at a specific slot index
+    ///
+    /// If the data doesn't fit in a single slot, this method automatically continues
+    /// to subsequent slots, erasing them as needed. Returns the next free slot index
+    /// and the checksum of the savegame that was just written.
+    ///
+    /// On flash with an erase sector size, slots that don't start a sector are
+    /// expected to be erased already, so writes should continue at the next free
+    /// slot returned by a previous write or [`Storage::scan`].
+    ///
+    /// Panics if the savegame is longer than [`Slot::MAX_LEN`].
     pub fn write(
         &mut self,
-        mut idx: usize,
+        idx: usize,
+        prev: Chksum,
+        data: &[u8],
+    ) -> Result<(usize, Chksum), F::Error> {
+        self.write_slot(idx, prev, &[data], 0)
+    }
+
+    /// Write a savegame from multiple buffers starting at a specific slot index
+    ///
+    /// Like [`Storage::write`], but the savegame is the concatenation of
+    /// `parts`, so it doesn't need to be copied into a single buffer first.
+    pub fn write_vectored(
+        &mut self,
+        idx: usize,
+        prev: Chksum,
+        parts: &[&[u8]],
+    ) -> Result<(usize, Chksum), F::Error> {
+        self.write_slot(idx, prev, parts, 0)
+    }
+
+    /// Write a savegame from the concatenation of `parts` with the given slot
+    /// header flags
+    fn write_slot(
+        &mut self,
+        idx: usize,
         prev: Chksum,
-        mut data: &mut [u8],
+        parts: &[&[u8]],
+        flags: u8,
     ) -> Result<(usize, Chksum), F::Error> {
-        let slot = Slot::create(idx, prev, data);
+        let idx = idx % SLOT_COUNT;
+        let flags = flags | self.lap_flag(idx);
+        let mut hasher = chksum::Hasher::new(prev);
+        let mut len = 0usize;
+        for part in parts {
+            hasher.update(part);
+            len = len.saturating_add(part.len());
+        }
+        let len = len.saturating_add(self.overhead());
+        // The flags share the length field
+        assert!(len <= Slot::MAX_LEN, "savegame exceeds Slot::MAX_LEN");
+        let chksum = hasher.finish();
         let slot_addr = self.addr(idx);
-        self.flash.erase(slot_addr)?;
+        self.erase_slot(slot_addr)?;
+
+        // Subsequent slots are only erased if more data remains
+        let mut cursor = self.write_cursor(idx);
+        #[cfg(feature = "encrypt")]
+        let chksum = match self.encrypt_key().cloned() {
+            Some(key) => {
+                let (iv, mut cipher) = encrypt::seal(&key, prev, flags, parts);
+                self.write_data(&mut cursor, &iv)?;
+
+                // The checksum covers the ciphertext, so it doesn't leak the plaintext
+                let mut hasher = chksum::Hasher::new(prev);
+                let mut buf = [0u8; MAX_WRITE_SIZE];
+                for chunk in parts.iter().flat_map(|part| part.chunks(MAX_WRITE_SIZE)) {
+                    let buf = &mut buf[..chunk.len()];
+                    buf.copy_from_slice(chunk);
+                    cipher.apply_keystream(buf);
+                    hasher.update(buf);
+                    self.write_data(&mut cursor, buf)?;
+                }
+                hasher.finish()
+            }
+            None => {
+                for part in parts {
+                    self.write_data(&mut cursor, part)?;
+                }
+                chksum
+            }
+        };
+        #[cfg(not(feature = "encrypt"))]
+        for part in parts {
+            self.write_data(&mut cursor, part)?;
+        }
+
+        let slot = Slot {
+            idx,
+            chksum,
+            len: len as u32,
+            prev,
+            flags,
+        };
+        #[cfg(feature = "mac")]
+        if let Some(key) = self.mac_key() {
+            let mut mac = Mac::new(key, &slot.to_bytes());
+            for part in parts {
+                mac.update(part);
+            }
+            let tag = mac.finalize();
+            self.write_data(&mut cursor, &tag)?;
+        }
+        self.flush(&mut cursor)?;
+
+        // Write header last, to finalize the slot
+        self.write_header(&slot)?;
+
+        let next = cursor.idx.saturating_add(1) % SLOT_COUNT;
+        Ok((next, slot.chksum))
+    }
+
+    /// Write a compressed savegame starting at a specific slot index
+    ///
+    /// Like [`Storage::write`], but the data is compressed into `scratch`
+    /// first, see the [`compress`] module for details. `scratch` must hold
+    /// [`compress::max_size`] bytes for the length of `data`, otherwise or if
+    /// the compressed data isn't smaller the savegame is written uncompressed.
+    #[cfg(feature = "compress")]
+    pub fn write_compressed(
+        &mut self,
+        idx: usize,
+        prev: Chksum,
+        data: &[u8],
+        scratch: &mut [u8],
+    ) -> Result<(usize, Chksum), F::Error> {
+        match compress::compress(data, scratch) {
+            Some(size) => self.write_slot(idx, prev, &[&scratch[..size]], Slot::FLAG_COMPRESSED),
+            None => self.write(idx, prev, data),
+        }
+    }
+
+    /// Write a static-sized savegame directly into a single slot
+    ///
+    /// This is a more lightweight write operation for fixed-size data that fits
+    /// within a single slot (excluding the header). The size must not exceed
+    /// `SLOT_SIZE - Slot::HEADER_SIZE`. With [`Storage::with_key`] or
+    /// [`Storage::with_encryption`] this is the same as [`Storage::write`], as
+    /// the MAC or IV may not fit into the slot.
+    pub fn write_static<const SIZE: usize>(
+        &mut self,
+        mut idx: usize,
+        prev: Chksum,
+        data: &[u8; SIZE],
+    ) -> Result<(usize, Chksum), F::Error> {
+        // Sanity check
+        const {
+            let space_available = SLOT_SIZE
+                .checked_sub(Self::HEADER_SPACE)
+                .expect("Invalid SLOT_SIZE, Slot::HEADER_SIZE doesn't fit");
+            assert!(SIZE <= space_available);
+        }
+
+        if self.overhead() > 0 {
+            return self.write(idx, prev, data);
+        }
+
+        // Prepare slot header
+        let mut slot = Slot::create(idx, prev, data);
+        slot.flags = self.lap_flag(idx % SLOT_COUNT);
+        let slot_addr = self.addr(idx);
+        self.erase_slot(slot_addr)?;
+
+        // Write data directly after header
+        let addr = slot_addr.saturating_add(Self::HEADER_SPACE as u32);
+        self.write_padded(addr, data)?;
+        idx = idx.saturating_add(1) % SLOT_COUNT;
+
+        // Write header last, to finalize the slot
+        self.write_header(&slot)?;
+
+        Ok((idx, slot.chksum))
+    }
+
+    /// Append a new savegame at the next free slot
+    ///
+    /// The new savegame indicates it's an update to the previous savegame,
+    /// when fully written the scanner should find it as the most recent savegame.
+    pub fn append(&mut self, data: &[u8]) -> Result<(), F::Error> {
+        let (idx, chksum) = self.write(self.idx, self.prev, data)?;
+        self.appended(idx, chksum);
+        Ok(())
+    }
+
+    /// Append a new savegame from multiple buffers at the next free slot
+    ///
+    /// The buffers are written back to back, so a savegame made of several
+    /// structs doesn't need a temporary buffer for all of them. The checksum
+    /// covers their concatenation, it reads back like a single buffer.
+    pub fn append_vectored(&mut self, parts: &[&[u8]]) -> Result<(), F::Error> {
+        let (idx, chksum) = self.write_vectored(self.idx, self.prev, parts)?;
+        self.appended(idx, chksum);
+        Ok(())
+    }
+
+    /// Update the internal state after a full savegame was appended
+    pub(crate) const fn appended(&mut self, next: usize, chksum: Chksum) {
+        #[cfg(feature = "delta")]
+        {
+            self.chain = Some((self.idx, 0, None));
+        }
+        self.head = Some(self.idx);
+        self.move_to(next);
+        self.prev = chksum;
+    }
+
+    /// The largest savegame in bytes that fits into the next free slots,
+    /// without overwriting the latest savegame
+    ///
+    /// Erasing a sector clears the slots behind the written ones as well, so
+    /// on flash with an erase sector size the slots stop at the sector of the
+    /// latest savegame.
+    #[cfg(feature = "io")]
+    pub(crate) fn capacity(&self) -> usize {
+        let sector = |idx: usize| self.addr(idx) as usize / F::ERASE_SIZE;
+        let mut slots = 0;
+        let mut idx = self.idx;
+        while slots < SLOT_COUNT {
+            if let Some(head) = self.head {
+                let erases =
+                    F::NEEDS_ERASE && (self.addr(idx) as usize).is_multiple_of(F::ERASE_SIZE);
+                if idx == head || (erases && sector(idx) == sector(head)) {
+                    break;
+                }
+            }
+            slots += 1;
+            idx = (idx + 1) % SLOT_COUNT;
+        }
+        Self::data_space(slots)
+    }
+
+    /// Start streaming a savegame into the next free slot
+    ///
+    /// Returns a cursor at its data and the checksum of the previous savegame.
+    #[cfg(feature = "io")]
+    pub(crate) fn begin_append(&mut self) -> Result<(Cursor, Chksum), F::Error> {
+        self.erase_slot(self.addr(self.idx))?;
+        Ok((self.write_cursor(self.idx), self.prev))
+    }
+
+    /// Skip the slots of an unfinished streamed savegame
+    ///
+    /// The slots were written without a header, so they aren't erased and
+    /// can't be programmed again on flash with an erase sector size.
+    #[cfg(feature = "io")]
+    pub(crate) fn skip(&mut self, cursor: &Cursor) -> Result<(), F::Error> {
+        self.skip_to(cursor.idx.saturating_add(1) % SLOT_COUNT)
+    }
+
+    /// Append a new compressed savegame at the next free slot
+    ///
+    /// Like [`Storage::append`], see [`Storage::write_compressed`] for the
+    /// use of `scratch`.
+    #[cfg(feature = "compress")]
+    pub fn append_compressed(&mut self, data: &[u8], scratch: &mut [u8]) -> Result<(), F::Error> {
+        let (idx, chksum) = self.write_compressed(self.idx, self.prev, data, scratch)?;
+        // Compressed savegames can't be used as checkpoints
+        #[cfg(feature = "delta")]
+        {
+            self.chain = None;
+        }
+        self.head = Some(self.idx);
+        self.move_to(idx);
+        self.prev = chksum;
+        Ok(())
+    }
+
+    /// Append a static-sized savegame into the next free slot
+    ///
+    /// This is a more lightweight write operation for fixed-size data that fits
+    /// within a single slot (excluding the header). The size must not exceed
+    /// `SLOT_SIZE - Slot::HEADER_SIZE`.
+    pub fn append_static<const SIZE: usize>(&mut self, data: &[u8; SIZE]) -> Result<(), F::Error> {
+        let (idx, chksum) = self.write_static(self.idx, self.prev, data)?;
+        self.appended(idx, chksum);
+        Ok(())
+    }
+
+    /// Append a new delta savegame at the next free slot
+    ///
+    /// Only the changes from `base`, the data of the previous savegame, are
+    /// encoded into `scratch` and written, see the [`delta`] module for
+    /// details. A full savegame is written as a checkpoint instead after
+    /// [`Storage::set_checkpoint_interval`] delta savegames, if the patch
+    /// doesn't fit into `scratch` or isn't smaller than `data`, or if the
+    /// checkpoint of the previous savegame isn't known. The first
+    /// `append_delta` after [`Storage::scan`] finds it by following the chain.
+    ///
+    /// Checkpoints are also written early so the chain of the previous
+    /// savegame is never overwritten by the next savegame of the same size,
+    /// keeping it readable after a power failure.
+    ///
+    /// `base` must be the data of the latest savegame, otherwise the new
+    /// savegame would fail verification in [`Storage::read`]. Returns `false`
+    /// without writing anything if it isn't, unless a checkpoint is due.
+    #[cfg(feature = "delta")]
+    pub fn append_delta(
+        &mut self,
+        data: &[u8],
+        base: &[u8],
+        scratch: &mut [u8],
+    ) -> Result<bool, F::Error> {
+        let mut chain = self
+            .chain
+            .filter(|&(_, deltas, _)| deltas < self.checkpoint_interval);
+        if let Some((_, _, known @ None)) = &mut chain {
+            *known = self.head_chksum()?;
+        }
+
+        let patch = match chain {
+            Some((checkpoint, deltas, Some(head))) => {
+                if Chksum::hash(Chksum::zero(), base) != head {
+                    return Ok(false);
+                }
+                delta::diff(base, data, scratch)
+                    .filter(|&size| {
+                        size < data.len() && self.chain_fits(checkpoint, size, data.len())
+                    })
+                    .map(|size| (checkpoint, deltas, size))
+            }
+            _ => None,
+        };
+
+        let chksum = Chksum::hash(Chksum::zero(), data);
+        let (idx, prev) = match patch {
+            Some((checkpoint, deltas, size)) => {
+                let result =
+                    self.write_slot(self.idx, self.prev, &[&scratch[..size]], Slot::FLAG_DELTA)?;
+                self.chain = Some((checkpoint, deltas + 1, Some(chksum)));
+                result
+            }
+            None => {
+                let (idx, prev) = self.write(self.idx, self.prev, data)?;
+                self.appended(idx, prev);
+                if let Some((_, _, known)) = &mut self.chain {
+                    *known = Some(chksum);
+                }
+                return Ok(true);
+            }
+        };
+        self.head = Some(self.idx);
+        self.move_to(idx);
+        self.prev = prev;
+        Ok(true)
+    }
+
+    /// Set the number of delta savegames written between two checkpoints
+    ///
+    /// Defaults to [`delta::CHECKPOINT_INTERVAL`]. Longer chains save more
+    /// space, but [`Storage::read`] has to follow the whole chain.
+    #[cfg(feature = "delta")]
+    pub const fn set_checkpoint_interval(&mut self, interval: usize) {
+        self.checkpoint_interval = interval;
+    }
+
+    /// Reset internal state to initial values
+    ///
+    /// This does not erase any data, but causes the next write to start at slot 0
+    /// with a zeroed previous checksum.
+    pub const fn reset(&mut self) {
+        self.idx = 0;
+        self.prev = Chksum::zero();
+        self.head = None;
+        self.lap = false;
+        #[cfg(feature = "delta")]
+        {
+            self.chain = None;
+        }
+    }
+
+    /// Consume the storage manager and return the underlying flash device
+    ///
+    /// This can be used to retrieve the flash device after all storage operations
+    /// are complete.
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{
+        AlignedMockFlash, MeasuredMockFlash, MeasuredStats, MockFlash, SectorMockFlash,
+    };
+    use core::convert::Infallible;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use std::vec::Vec;
+
+    const SLOT_SIZE: usize = 64;
+    const SLOT_COUNT: usize = 8;
+    const SIZE: usize = SLOT_SIZE * SLOT_COUNT;
+
+    const fn mock_storage() -> Storage<MockFlash<SIZE>, SLOT_SIZE, SLOT_COUNT> {
+        let flash = MockFlash::<SIZE>::new();
+        Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash)
+    }
+
+    const fn mock_zeroed_storage() -> Storage<MockFlash<SIZE, 0x00>, SLOT_SIZE, SLOT_COUNT> {
+        let flash = MockFlash::<SIZE, 0x00>::new();
+        Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash)
+    }
+
+    const fn mock_sector_storage()
+    -> Storage<SectorMockFlash<SLOT_SIZE, SLOT_COUNT>, SLOT_SIZE, SLOT_COUNT> {
+        let flash = SectorMockFlash::<SLOT_SIZE, SLOT_COUNT>::new();
+        Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash)
+    }
+
+    const PACKED_SECTOR_SIZE: usize = SLOT_SIZE * 4;
+
+    const fn mock_packed_storage() -> Storage<
+        SectorMockFlash<PACKED_SECTOR_SIZE, { SIZE / PACKED_SECTOR_SIZE }>,
+        SLOT_SIZE,
+        SLOT_COUNT,
+    > {
+        let flash = SectorMockFlash::<PACKED_SECTOR_SIZE, { SIZE / PACKED_SECTOR_SIZE }>::new();
+        Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash)
+    }
+
+    fn mock_measured_storage() -> Storage<MeasuredMockFlash<SIZE>, SLOT_SIZE, SLOT_COUNT> {
+        let flash = MeasuredMockFlash::<SIZE>::new();
+        Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash)
+    }
+
+    fn mock_measured_no_erase_storage()
+    -> Storage<MeasuredMockFlash<SIZE, false>, SLOT_SIZE, SLOT_COUNT> {
+        let flash = MeasuredMockFlash::<SIZE, false>::new();
+        Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash)
+    }
+
+    fn test_storage_empty_scan<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        let Ok(slot) = storage.scan();
+        assert_eq!(slot, None);
+    }
+
+    #[test]
+    fn test_at24cxx_storage_empty_scan() {
+        let mut storage = mock_storage();
+        test_storage_empty_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_zeroed_storage_empty_scan() {
+        let mut storage = mock_zeroed_storage();
+        test_storage_empty_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_w25qxx_storage_empty_scan() {
+        let mut storage = mock_sector_storage();
+        test_storage_empty_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_packed_storage_empty_scan() {
+        let mut storage = mock_packed_storage();
+        test_storage_empty_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_measured_storage_empty_scan() {
+        let mut storage = mock_measured_storage();
+        test_storage_empty_scan(&mut storage);
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 8,
+                write: 0,
+                erase: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_storage_write() {
+        let mut storage = mock_storage();
+
+        let data = *b"hello world";
+        storage.append(&data);
+
+        let mut buf = [0u8; Slot::HEADER_SIZE];
+        storage.flash.read(0, &mut buf);
+        let slot = Slot::from_bytes(0, buf);
+        assert_eq!(
+            slot,
+            Slot {
+                idx: 0,
+                chksum: Chksum::hash(Chksum::zero(), &data),
+                len: data.len() as u32,
+                prev: Chksum::zero(),
+                flags: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_storage_write_static() {
+        let mut storage = mock_storage();
+
+        let data = *b"hello world";
+        storage.append_static(&data);
+
+        let mut buf = [0u8; Slot::HEADER_SIZE];
+        storage.flash.read(0, &mut buf);
+        let slot = Slot::from_bytes(0, buf);
+        assert_eq!(
+            slot,
+            Slot {
+                idx: 0,
+                chksum: Chksum::hash(Chksum::zero(), &data),
+                len: data.len() as u32,
+                prev: Chksum::zero(),
+                flags: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_storage_write_immutable() {
+        // Larger than the internal chunk buffer and spanning several slots
+        static DATA: [u8; 150] = [0x5A; 150];
+        const STATE: [u8; 11] = *b"hello world";
+
+        let mut storage = mock_sector_storage();
+        let Ok(()) = storage.append(&DATA);
+        let mut buf = [0u8; 150];
+        let Ok(Some(data)) = storage.read(0, &mut buf) else {
+            panic!("no savegame");
+        };
+        assert_eq!(data, &DATA);
+
+        let Ok(()) = storage.append_static(&STATE);
+        let Ok(Some(slot)) = storage.scan() else {
+            panic!("no savegame");
+        };
+        let mut buf = [0u8; 11];
+        let Ok(Some(data)) = storage.read(slot.idx, &mut buf) else {
+            panic!("no savegame");
+        };
+        assert_eq!(data, &STATE);
+    }
+
+    fn test_storage_write_vectored<F: Flash<Error = Infallible>>(
+        vectored: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+        contiguous: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        let parts: [&[u8]; 4] = [b"player", &[0x22; SLOT_SIZE + 3], b"", b"settings"];
+        let data = parts.concat();
+        for _ in 0..3 {
+            let Ok(()) = vectored.append_vectored(&parts);
+            let Ok(()) = contiguous.append(&data);
+        }
+
+        // Same as writing the concatenation
+        for idx in 0..SLOT_COUNT {
+            let mut expected = [0u8; SLOT_SIZE];
+            let Ok(()) = contiguous.flash.read(contiguous.addr(idx), &mut expected);
+            let mut actual = [0u8; SLOT_SIZE];
+            let Ok(()) = vectored.flash.read(vectored.addr(idx), &mut actual);
+            assert_eq!(actual, expected);
+        }
+
+        let Ok(Some(slot)) = vectored.scan() else {
+            panic!("no savegame");
+        };
+        let mut buf = [0u8; SLOT_SIZE * 2];
+        let Ok(Some(read)) = vectored.read(slot.idx, &mut buf) else {
+            panic!("no savegame");
+        };
+        assert_eq!(read, &data[..]);
+    }
+
+    #[test]
+    fn test_storage_write_vectored_at24cxx() {
+        test_storage_write_vectored(&mut mock_storage(), &mut mock_storage());
+    }
+
+    #[test]
+    fn test_storage_write_vectored_packed() {
+        test_storage_write_vectored(&mut mock_packed_storage(), &mut mock_packed_storage());
+    }
+
+    fn test_storage_write_scan<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        let data = *b"hello world";
+        storage.append(&data);
+
+        let Ok(scan) = storage.scan();
+        assert_eq!(
+            scan,
+            Some(Slot {
+                idx: 0,
+                chksum: Chksum::hash(Chksum::zero(), &data),
+                len: data.len() as u32,
+                prev: Chksum::zero(),
+                flags: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_at24cxx_storage_write_scan() {
+        let mut storage = mock_storage();
+        test_storage_write_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_zeroed_storage_write_scan() {
+        let mut storage = mock_zeroed_storage();
+        test_storage_write_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_w25qxx_storage_write_scan() {
+        let mut storage = mock_sector_storage();
+        test_storage_write_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_packed_storage_write_scan() {
+        let mut storage = mock_packed_storage();
+        test_storage_write_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_measured_storage_write_scan() {
+        let mut storage = mock_measured_storage();
+        test_storage_write_scan(&mut storage);
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 20,
+                write: 23,
+                erase: 1,
+            }
+        );
+    }
+
+    fn test_storage_write_read<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        let data = *b"hello world";
+        storage.append(&data);
+
+        let mut buf = [0u8; 1024];
+        let Ok(slice) = storage.read(0, &mut buf);
+
+        assert_eq!(slice.map(|s| &*s), Some("hello world".as_bytes()));
+    }
+
+    #[test]
+    fn test_at24cxx_storage_write_read() {
+        let mut storage = mock_storage();
+        test_storage_write_read(&mut storage);
+    }
+
+    #[test]
+    fn test_zeroed_storage_write_read() {
+        let mut storage = mock_zeroed_storage();
+        test_storage_write_read(&mut storage);
+    }
+
+    #[test]
+    fn test_w25qxx_storage_write_read() {
+        let mut storage = mock_sector_storage();
+        test_storage_write_read(&mut storage);
+    }
+
+    #[test]
+    fn test_packed_storage_write_read() {
+        let mut storage = mock_packed_storage();
+        test_storage_write_read(&mut storage);
+    }
+
+    #[test]
+    fn test_measured_storage_write_read() {
+        let mut storage = mock_measured_storage();
+        test_storage_write_read(&mut storage);
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 24,
+                write: 23,
+                erase: 1,
+            }
+        );
+    }
+
+    fn test_storage_write_wrap_around<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        for num in 0..(SLOT_COUNT as u32 * 3 + 2) {
+            let mut buf = [0u8; 6];
+            num.to_be_bytes().iter().enumerate().for_each(|(i, b)| {
+                buf[i] = *b;
+            });
+            storage.append(&buf);
+        }
+
+        let slot = storage.scan().unwrap().unwrap();
+        assert_eq!(slot.idx, 1);
+        assert_eq!(storage.idx, 2);
+
+        let mut buf = [0u8; 32];
+        let Ok(slice) = storage.read(slot.idx, &mut buf);
+        assert_eq!(slice, Some(&mut [0, 0, 0, 25, 0, 0][..]));
+    }
+
+    #[test]
+    fn test_at24cxx_storage_write_wrap_around() {
+        let mut storage = mock_storage();
+        test_storage_write_wrap_around(&mut storage);
+    }
+
+    #[test]
+    fn test_zeroed_storage_write_wrap_around() {
+        let mut storage = mock_zeroed_storage();
+        test_storage_write_wrap_around(&mut storage);
+    }
+
+    #[test]
+    fn test_w25qxx_storage_write_wrap_around() {
+        let mut storage = mock_sector_storage();
+        test_storage_write_wrap_around(&mut storage);
+    }
+
+    #[test]
+    fn test_packed_storage_write_wrap_around() {
+        let mut storage = mock_packed_storage();
+        test_storage_write_wrap_around(&mut storage);
+    }
+
+    #[test]
+    fn test_measured_no_erase_storage_write_read() {
+        let mut storage = mock_measured_no_erase_storage();
+        test_storage_write_read(&mut storage);
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 24,
+                write: 24,
+                erase: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_measured_storage_write_wrap_around() {
+        let mut storage = mock_measured_storage();
+        test_storage_write_wrap_around(&mut storage);
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 140,
+                write: 486,
+                erase: 26,
+            }
+        );
+    }
+
+    #[test]
+    fn test_measured_no_erase_storage_write_wrap_around() {
+        let mut storage = mock_measured_no_erase_storage();
+        test_storage_write_wrap_around(&mut storage);
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 140,
+                write: 494,
+                erase: 0,
+            }
+        );
+    }
+
+    fn test_storage_big_write<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        let buf = [b'A'; SLOT_SIZE * 5];
+        storage.append(&buf);
+        let slot = storage.scan().unwrap().unwrap();
+        assert_eq!(
+            slot,
+            Slot {
+                idx: 0,
+                chksum: Chksum::hash(Chksum::zero(), &buf),
+                len: buf.len() as u32,
+                prev: Chksum::zero(),
+                flags: 0,
+            }
+        );
+
+        let mut buf2 = [0u8; 512];
+        let Ok(slice) = storage.read(slot.idx, &mut buf2);
+        assert_eq!(slice.map(|s| &*s), Some(&buf[..]));
+
+        let buf = [b'B'; SLOT_SIZE * 5];
+        storage.append(&buf);
+        let new_slot = storage.scan().unwrap().unwrap();
+        assert_eq!(
+            new_slot,
+            Slot {
+                idx: 6,
+                chksum: Chksum::hash(slot.chksum, &buf),
+                len: buf.len() as u32,
+                prev: slot.chksum,
+                flags: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_at24cxx_storage_big_write() {
+        let mut storage = mock_storage();
+        test_storage_big_write(&mut storage);
+    }
+
+    #[test]
+    fn test_zeroed_storage_big_write() {
+        let mut storage = mock_zeroed_storage();
+        test_storage_big_write(&mut storage);
+    }
+
+    #[test]
+    fn test_w25qxx_storage_big_write() {
+        let mut storage = mock_sector_storage();
+        test_storage_big_write(&mut storage);
+    }
+
+    #[test]
+    fn test_packed_storage_big_write() {
+        let mut storage = mock_packed_storage();
+        test_storage_big_write(&mut storage);
+    }
+
+    #[test]
+    fn test_measured_storage_big_write() {
+        let mut storage = mock_measured_storage();
+        test_storage_big_write(&mut storage);
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 382,
+                write: 675,
+                erase: 12,
+            }
+        );
+    }
+
+    #[test]
+    fn test_measured_no_erase_storage_big_write() {
+        let mut storage = mock_measured_no_erase_storage();
+        test_storage_big_write(&mut storage);
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 382,
+                write: 677,
+                erase: 0,
+            }
+        );
+    }
+
+    /// Records the longest single write to the flash
+    struct LongestWrite<F>(F, usize);
+
+    impl<F: Flash> Flash for LongestWrite<F> {
+        type Error = F::Error;
+
+        fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+            self.0.read(addr, buf)
+        }
+
+        fn write(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Self::Error> {
+            self.write_slice(addr, data)
+        }
+
+        fn write_slice(&mut self, addr: u32, data: &[u8]) -> Result<(), Self::Error> {
+            self.1 = self.1.max(data.len());
+            self.0.write_slice(addr, data)
+        }
+
+        fn erase(&mut self, addr: u32) -> Result<(), Self::Error> {
+            self.0.erase(addr)
+        }
+    }
+
+    #[test]
+    fn test_write_not_split() {
+        let flash = LongestWrite(MockFlash::<1024>::new(), 0);
+        let mut storage = Storage::<_, 256, 4>::new(flash);
+        let data = [0x11; 200];
+        let Ok(()) = storage.append(&data);
+        // Page programs aren't split into chunks of MAX_WRITE_SIZE
+        assert_eq!(storage.flash.1, data.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "savegame exceeds Slot::MAX_LEN")]
+    fn test_write_exceeding_max_len() {
+        // The length would overflow into the flags
+        let data = std::vec![0u8; Slot::MAX_LEN + 1];
+        let mut storage = mock_storage();
+        let _ = storage.append(&data);
+    }
+
+    fn test_append_after_scan<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        let big = [b'A'; SLOT_SIZE * 2];
+        storage.append(&big);
+        assert_eq!(storage.idx, 3);
+        storage.idx = 0;
+
+        storage.scan().unwrap();
+        assert_eq!(storage.idx, 3);
+        assert_eq!(storage.prev, Chksum::hash(Chksum::zero(), &big));
+    }
+
+    #[test]
+    fn test_at24cxx_append_after_scan() {
+        let mut storage = mock_storage();
+        test_append_after_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_zeroed_append_after_scan() {
+        let mut storage = mock_zeroed_storage();
+        test_append_after_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_w25qxx_append_after_scan() {
+        let mut storage = mock_sector_storage();
+        test_append_after_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_packed_append_after_scan() {
+        let mut storage = mock_packed_storage();
+        test_append_after_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_measured_append_after_scan() {
+        let mut storage = mock_measured_storage();
+        test_append_after_scan(&mut storage);
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 22,
+                write: 142,
+                erase: 3,
+            }
+        );
+    }
+
+    fn test_append_after_interrupted_write<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        let Ok(()) = storage.append(b"first");
+
+        // A savegame interrupted before its header was written
+        let mut cursor = storage.write_cursor(storage.idx);
+        let Ok(()) = storage.write_data(&mut cursor, &[0; SLOT_SIZE * 2]);
+        let Ok(()) = storage.flush(&mut cursor);
+
+        storage.reset();
+        let Ok(Some(_)) = storage.scan() else {
+            panic!("no savegame");
+        };
+        let data = *b"second!!";
+        let Ok(()) = storage.append(&data);
+
+        storage.reset();
+        let Ok(Some(slot)) = storage.scan() else {
+            panic!("no savegame");
+        };
+        let mut buf = [0u8; SLOT_SIZE];
+        let Ok(read) = storage.read(slot.idx, &mut buf);
+        assert_eq!(read.map(|data| &*data), Some(&data[..]));
+    }
+
+    #[test]
+    fn test_at24cxx_append_after_interrupted_write() {
+        test_append_after_interrupted_write(&mut mock_storage());
+    }
+
+    #[test]
+    fn test_zeroed_append_after_interrupted_write() {
+        test_append_after_interrupted_write(&mut mock_zeroed_storage());
+    }
+
+    #[test]
+    fn test_w25qxx_append_after_interrupted_write() {
+        test_append_after_interrupted_write(&mut mock_sector_storage());
+    }
+
+    #[test]
+    fn test_packed_append_after_interrupted_write() {
+        test_append_after_interrupted_write(&mut mock_packed_storage());
+    }
+
+    fn test_scan_with_hint<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        assert_eq!(storage.hint(), None);
+        let Ok(()) = storage.append(b"first");
+        let Ok(()) = storage.append(&[0x11; SLOT_SIZE]);
+        let Some(hint) = storage.hint() else {
+            panic!("no hint");
+        };
+        assert_eq!(ScanHint::from_bytes(hint.to_bytes()), hint);
+        let next = storage.idx;
+
+        // The hint survives a reboot
+        storage.reset();
+        let Ok(Some(slot)) = storage.scan_with_hint(hint) else {
+            panic!("no savegame");
+        };
+        assert_eq!(slot.idx, hint.idx);
+        assert_eq!(storage.hint(), Some(hint));
+        assert_eq!(storage.idx, next);
+
+        // A newer savegame makes the hint stale
+        let Ok(()) = storage.append(b"third");
+        let Some(newest) = storage.hint() else {
+            panic!("no hint");
+        };
+        storage.reset();
+        let Ok(Some(slot)) = storage.scan_with_hint(hint) else {
+            panic!("no savegame");
+        };
+        assert_eq!(slot.idx, newest.idx);
+        assert_eq!(storage.hint(), Some(newest));
+
+        // Corrupted hints fall back to a full scan
+        for hint in [
+            ScanHint {
+                idx: SLOT_COUNT,
+                ..newest
+            },
+            ScanHint {
+                chksum: Chksum::zero(),
+                ..newest
+            },
+        ] {
+            storage.reset();
+            let Ok(Some(slot)) = storage.scan_with_hint(hint) else {
+                panic!("no savegame");
+            };
+            assert_eq!(slot.idx, newest.idx);
+        }
+
+        // Erased savegames are not trusted
+        let Ok(()) = storage.erase_all();
+        let Ok(scan) = storage.scan_with_hint(newest);
+        assert_eq!(scan, None);
+        assert_eq!(storage.hint(), None);
+    }
+
+    #[test]
+    fn test_at24cxx_scan_with_hint() {
+        let mut storage = mock_storage();
+        test_scan_with_hint(&mut storage);
+    }
+
+    #[test]
+    fn test_w25qxx_scan_with_hint() {
+        let mut storage = mock_sector_storage();
+        test_scan_with_hint(&mut storage);
+    }
+
+    #[test]
+    fn test_packed_scan_with_hint() {
+        let mut storage = mock_packed_storage();
+        test_scan_with_hint(&mut storage);
+    }
+
+    #[test]
+    fn test_measured_scan_with_hint() {
+        let mut storage = mock_measured_storage();
+        for i in 0..5 {
+            let Ok(()) = storage.append(&[i; 8]);
+        }
+        let Some(hint) = storage.hint() else {
+            panic!("no hint");
+        };
+
+        storage.reset();
+        storage.flash.stats = MeasuredStats::default();
+        let Ok(scan) = storage.scan();
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 63,
+                write: 0,
+                erase: 0,
+            }
+        );
+
+        // Only the hinted savegame and the slot after it are read
+        storage.reset();
+        storage.flash.stats = MeasuredStats::default();
+        let Ok(hinted) = storage.scan_with_hint(hint);
+        assert_eq!(hinted, scan);
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 13,
+                write: 0,
+                erase: 0,
+            }
+        );
+    }
+
+    fn test_scan_fast<F: Flash>(storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>)
+    where
+        F::Error: fmt::Debug,
+    {
+        assert_eq!(storage.scan_fast().unwrap(), None);
+
+        for num in 0..(SLOT_COUNT as u8 * 4) {
+            // Vary the length to wrap around at different slots
+            let data = [num; SLOT_SIZE * 2];
+            let len = (num as usize * 23) % data.len();
+            storage.append(&data[..len]).unwrap();
+            let next = storage.idx;
+
+            let expected = storage.scan().unwrap();
+            storage.reset();
+            let slot = storage.scan_fast().unwrap();
+            assert_eq!(slot, expected);
+            assert_eq!(storage.idx, next);
+        }
+
+        // A broken latest savegame falls back to the full scan
+        let head = storage.hint().unwrap().idx;
+        storage.erase(head).unwrap();
+        let expected = storage.scan().unwrap();
+        storage.reset();
+        assert_eq!(storage.scan_fast().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_at24cxx_scan_fast() {
+        test_scan_fast(&mut mock_storage());
+    }
+
+    #[test]
+    fn test_zeroed_scan_fast() {
+        test_scan_fast(&mut mock_zeroed_storage());
+    }
+
+    #[test]
+    fn test_w25qxx_scan_fast() {
+        test_scan_fast(&mut mock_sector_storage());
+    }
+
+    #[test]
+    fn test_packed_scan_fast() {
+        test_scan_fast(&mut mock_packed_storage());
+    }
+
+    #[test]
+    fn test_aligned_scan_fast() {
+        let flash = AlignedMockFlash::<8, { SLOT_SIZE * 2 }, { SLOT_COUNT / 2 }>::new();
+        test_scan_fast(&mut Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash));
+    }
+
+    #[test]
+    fn test_measured_scan_fast() {
+        const SLOT_SIZE: usize = 16;
+        const SLOT_COUNT: usize = 256;
+        let flash = MeasuredMockFlash::<{ SLOT_SIZE * SLOT_COUNT }>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash);
+        for num in 0..300u32 {
+            let Ok(()) = storage.append(&num.to_be_bytes());
+        }
+
+        storage.reset();
+        storage.flash.stats = MeasuredStats::default();
+        let Ok(scan) = storage.scan();
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 3072,
+                write: 0,
+                erase: 0,
+            }
+        );
+
+        storage.reset();
+        storage.flash.stats = MeasuredStats::default();
+        let Ok(fast) = storage.scan_fast();
+        assert_eq!(fast, scan);
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 32,
+                write: 0,
+                erase: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_scan_report() {
+        use SlotStatus::*;
+        let mut storage = mock_storage();
+        let Ok(report) = storage.scan_report();
+        assert_eq!(report.slots, [Blank; SLOT_COUNT]);
+        assert_eq!(report.head, None);
+
+        let Ok(()) = storage.append(b"first");
+        let Ok(()) = storage.append(&[0x11; SLOT_SIZE * 2]);
+        let Ok(()) = storage.append(b"third");
+        let Ok(()) = storage.append(&[0x22; SLOT_SIZE]);
+        let Ok(report) = storage.scan_report();
+        assert_eq!(
+            report.slots,
+            [
+                Stale,
+                Stale,
+                Continuation,
+                Continuation,
+                Stale,
+                Head,
+                Continuation,
+                Blank
+            ]
+        );
+        assert_eq!(report.count(Continuation), 3);
+        let Ok(scan) = storage.scan();
+        assert_eq!(report.head, scan);
+
+        // Lose the header of a savegame spanning several slots
+        let Ok(()) = storage.flash.erase(storage.addr(1));
+        // Garbage in front of a header and instead of a continuation marker
+        let Ok(()) = storage.flash.write(storage.addr(0) + 8, &mut [0x80]);
+        let Ok(()) = storage.flash.write(storage.addr(7), &mut [0x90]);
+        let Ok(report) = storage.scan_report();
+        assert_eq!(
+            report.slots,
+            [
+                Corrupt,
+                Blank,
+                Orphan,
+                Orphan,
+                Stale,
+                Head,
+                Continuation,
+                Corrupt
+            ]
+        );
+        let Ok(scan) = storage.scan();
+        assert_eq!(report.head, scan);
+    }
+
+    /// Payloads of random bytes, or made of valid headers at any offset
+    fn payload() -> impl Strategy<Value = Vec<u8>> {
+        prop_oneof![
+            vec(any::<u8>(), 0..SLOT_SIZE * 3),
+            (any::<[u8; 4]>(), 0..SLOT_SIZE * 3, 0..Slot::HEADER_SIZE).prop_map(
+                |(seed, len, shift)| {
+                    let prev = Chksum::hash(Chksum::zero(), &seed);
+                    let header = Slot::create(0, prev, &seed).to_bytes();
+                    header
+                        .iter()
+                        .cycle()
+                        .skip(shift)
+                        .take(len)
+                        .copied()
+                        .collect()
+                }
+            ),
+        ]
+    }
+
+    fn check_no_false_headers<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+        saves: &[Vec<u8>],
+    ) -> Result<(), TestCaseError> {
+        // Whether the start of each slot was last written with a header
+        let mut headers = [false; SLOT_COUNT];
+        for data in saves {
+            let start = storage.idx;
+            let Ok(()) = storage.append(data);
+            let mut idx = start;
+            loop {
+                headers[idx] = idx == start;
+                idx = (idx + 1) % SLOT_COUNT;
+                if idx == storage.idx {
+                    break;
+                }
+            }
+        }
+
+        for (idx, &header) in headers.iter().enumerate() {
+            let Ok(slot) = storage.scan_slot(idx);
+            prop_assert!(header || slot.is_none(), "false header in slot {}", idx);
+        }
+
+        let Ok(Some(slot)) = storage.scan() else {
+            return Err(TestCaseError::fail("no savegame"));
+        };
+        let mut buf = [0u8; SLOT_SIZE * 3];
+        let Ok(data) = storage.read(slot.idx, &mut buf);
+        prop_assert_eq!(data.map(|data| &*data), saves.last().map(|data| &data[..]));
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_at24cxx_no_false_headers(saves in vec(payload(), 1..24)) {
+            check_no_false_headers(&mut mock_storage(), &saves)?;
+        }
+
+        #[test]
+        fn prop_zeroed_no_false_headers(saves in vec(payload(), 1..24)) {
+            check_no_false_headers(&mut mock_zeroed_storage(), &saves)?;
+        }
+
+        #[test]
+        fn prop_packed_no_false_headers(saves in vec(payload(), 1..24)) {
+            check_no_false_headers(&mut mock_packed_storage(), &saves)?;
+        }
+    }
+
+    /// Operation on the storage in the model test
+    #[derive(Clone, Debug)]
+    enum Op {
+        Append(Vec<u8>),
+        AppendStatic([u8; 2]),
+        /// Erase a slot, or the latest savegame
+        Erase(Option<usize>),
+        EraseAll,
+        /// Reset without a scan, so the next savegame starts over at slot 0
+        Reset,
+        Scan,
+        ScanFast,
+        Reboot,
+    }
+
+    fn op(max_len: usize) -> impl Strategy<Value = Op> {
+        // Leave room for the sequence number that makes savegames unique
+        let max_len = max_len - 2;
+        prop_oneof![
+            4 => vec(any::<u8>(), 0..=max_len).prop_map(Op::Append),
+            2 => any::<[u8; 2]>().prop_map(Op::AppendStatic),
+            1 => proptest::option::of(0..64usize).prop_map(Op::Erase),
+            1 => Just(Op::EraseAll),
+            1 => Just(Op::Reset),
+            2 => Just(Op::Scan),
+            2 => Just(Op::ScanFast),
+            2 => Just(Op::Reboot),
+        ]
+    }
+
+    /// The largest savegame to append, so it fits into half of the slots
+    fn max_len<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize>() -> usize {
+        let header = Storage::<F, SLOT_SIZE, SLOT_COUNT>::HEADER_SPACE;
+        let marker = Storage::<F, SLOT_SIZE, SLOT_COUNT>::MARKER_SPACE;
+        (SLOT_SIZE - header) + (SLOT_COUNT / 2 - 1) * (SLOT_SIZE - marker)
+    }
+
+    /// A savegame appended in the model test
+    struct Save {
+        idx: usize,
+        chksum: Chksum,
+        prev: Chksum,
+        data: Vec<u8>,
+        /// Whether none of its data was overwritten or erased
+        intact: bool,
+    }
+
+    /// What the storage is expected to hold
+    struct Model<const SLOT_SIZE: usize, const SLOT_COUNT: usize> {
+        saves: Vec<Save>,
+        /// The savegame written into each slot, a savegame is only found while
+        /// the slot of its header still belongs to it
+        slots: [Option<usize>; SLOT_COUNT],
+        /// The savegame the next one is chained to
+        prev: Option<usize>,
+        /// Whether the next savegame starts over at slot 0 after a reset
+        detached: bool,
+        /// Whether the savegames were appended in order since all slots were
+        /// erased, so [`Storage::scan_fast`] finds the same as [`Storage::scan`]
+        clean: bool,
+    }
+
+    impl<const SLOT_SIZE: usize, const SLOT_COUNT: usize> Model<SLOT_SIZE, SLOT_COUNT> {
+        const fn new() -> Self {
+            Self {
+                saves: Vec::new(),
+                slots: [None; SLOT_COUNT],
+                prev: None,
+                detached: false,
+                clean: true,
+            }
+        }
+
+        /// The savegame whose header is still in slot `idx`
+        fn head(&self, idx: usize) -> Option<usize> {
+            self.slots[idx].filter(|&save| self.saves[save].idx == idx)
+        }
+
+        /// Erase slot `idx`, or only the start of it
+        fn clear(&mut self, idx: usize, whole: bool) {
+            let Some(save) = self.slots[idx] else {
+                return;
+            };
+            if self.saves[save].idx == idx {
+                self.slots[idx] = None;
+            } else if whole {
+                // Only the continuation marker is lost if the data remains
+                self.saves[save].intact = false;
+                self.slots[idx] = None;
+            }
+        }
+
+        /// Erase what [`Flash::erase`] erases at the start of slot `idx`
+        fn erase<F: Flash>(&mut self, idx: usize) {
+            if F::ERASE_SIZE == 1 {
+                self.clear(idx, false);
+                return;
+            }
+            let sector = idx * SLOT_SIZE / F::ERASE_SIZE * F::ERASE_SIZE;
+            let end = (sector + F::ERASE_SIZE).min(SLOT_SIZE * SLOT_COUNT);
+            for idx in sector / SLOT_SIZE..end.div_ceil(SLOT_SIZE) {
+                self.clear(idx, true);
+            }
+        }
+
+        /// Write a new savegame into the slots from `idx` up to `next`
+        fn append<F: Flash>(&mut self, save: Save, next: usize) {
+            let id = self.saves.len();
+            let mut idx = save.idx;
+            self.saves.push(save);
+            loop {
+                if F::NEEDS_ERASE && (idx * SLOT_SIZE).is_multiple_of(F::ERASE_SIZE) {
+                    self.erase::<F>(idx);
+                }
+                self.clear(idx, true);
+                self.slots[idx] = Some(id);
+                idx = (idx + 1) % SLOT_COUNT;
+                if idx == next {
+                    break;
+                }
+            }
+            self.prev = Some(id);
+        }
+
+        /// The savegame [`Storage::scan`] has to find
+        fn scan(&self) -> Option<usize> {
+            let mut current: Option<usize> = None;
+            for idx in 0..SLOT_COUNT {
+                let Some(save) = self.head(idx) else {
+                    continue;
+                };
+                if current.is_none_or(|current| self.saves[save].prev == self.saves[current].chksum)
+                {
+                    current = Some(save);
+                }
+            }
+            current
+        }
+
+        /// Continue after a savegame found by a scan
+        const fn found(&mut self, save: Option<usize>) {
+            if save.is_some() {
+                self.prev = save;
+                self.detached = false;
+            }
+        }
+    }
+
+    /// Check the savegame found by a scan is `expected`, and read it back
+    fn check_found<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+        slot: Option<Slot>,
+        model: &Model<SLOT_SIZE, SLOT_COUNT>,
+        expected: Option<usize>,
+    ) -> Result<(), TestCaseError>
+    where
+        F::Error: core::fmt::Debug,
+    {
+        let found = slot.as_ref().map(|slot| (slot.idx, slot.chksum));
+        let save = expected.map(|save| &model.saves[save]);
+        prop_assert_eq!(found, save.map(|save| (save.idx, save.chksum)));
+
+        // Data overwritten after the header was written reads back garbage
+        if let Some(save) = save.filter(|save| save.intact) {
+            let mut buf = [0u8; 1024];
+            let data = storage.read(save.idx, &mut buf).unwrap();
+            prop_assert_eq!(data.as_deref(), Some(save.data.as_slice()));
+        }
+        Ok(())
+    }
+
+    /// Scan like [`Storage::scan`], and check the result against the model
+    fn check_scan<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+        model: &mut Model<SLOT_SIZE, SLOT_COUNT>,
+    ) -> Result<(), TestCaseError>
+    where
+        F::Error: core::fmt::Debug,
+    {
+        let slot = storage.scan().unwrap();
+        let expected = model.scan();
+        check_found(storage, slot, model, expected)?;
+        model.found(expected);
+        Ok(())
+    }
+
+    fn check_model<F: Flash, const SLOT_SIZE: usize, const SLOT_COUNT: usize>(
+        flash: F,
+        ops: &[Op],
+    ) -> Result<(), TestCaseError>
+    where
+        F::Error: core::fmt::Debug,
+    {
+        let mut storage = Storage::<F, SLOT_SIZE, SLOT_COUNT>::new(flash);
+        let mut model = Model::<SLOT_SIZE, SLOT_COUNT>::new();
+
+        for (num, op) in ops.iter().enumerate() {
+            // Identical savegames can't be told apart by their checksum, so
+            // every savegame starts with a sequence number
+            let seq = (num as u16).to_be_bytes();
+            let data = match op {
+                Op::Append(data) => {
+                    let data = [&seq[..], data].concat();
+                    storage.append(&data).unwrap();
+                    data
+                }
+                Op::AppendStatic(data) => {
+                    let data = [seq[0], seq[1], data[0], data[1]];
+                    storage.append_static(&data).unwrap();
+                    data.to_vec()
+                }
+                Op::Erase(idx) => {
+                    let Some(idx) = idx
+                        .map(|idx| idx % SLOT_COUNT)
+                        .or_else(|| storage.hint().map(|hint| hint.idx))
+                    else {
+                        continue;
+                    };
+                    storage.erase(idx).unwrap();
+                    model.erase::<F>(idx);
+                    model.clean = false;
+                    continue;
+                }
+                Op::EraseAll => {
+                    storage.erase_all().unwrap();
+                    model.slots = [None; SLOT_COUNT];
+                    model.prev = None;
+                    model.detached = false;
+                    model.clean = true;
+                    continue;
+                }
+                Op::Reset => {
+                    storage.reset();
+                    model.prev = None;
+                    model.detached = true;
+                    continue;
+                }
+                Op::Scan => {
+                    check_scan(&mut storage, &mut model)?;
+                    continue;
+                }
+                Op::ScanFast => {
+                    let slot = storage.scan_fast().unwrap();
+                    let expected = if model.clean {
+                        model.scan()
+                    } else {
+                        // Erased slots and savegames written after a reset
+                        // may hide the latest savegame, but never show
+                        // another one than a valid header
+                        slot.as_ref().and_then(|slot| model.head(slot.idx))
+                    };
+                    check_found(&mut storage, slot, &model, expected)?;
+                    model.found(expected);
+                    continue;
+                }
+                Op::Reboot => {
+                    storage = Storage::new(storage.into_inner());
+                    model.prev = None;
+                    model.detached = true;
+                    check_scan(&mut storage, &mut model)?;
+                    continue;
+                }
+            };
+
+            // The savegame continues the chain, without overwriting the
+            // savegame it's chained to
+            let hint = storage.hint().unwrap();
+            let header = storage.read_header(hint.idx).unwrap();
+            let next = header.next_padded_slot::<SLOT_SIZE, SLOT_COUNT>(F::WRITE_SIZE);
+            let prev = model
+                .prev
+                .map_or(Chksum::zero(), |prev| model.saves[prev].chksum);
+            prop_assert_eq!(header.prev, prev);
+            if let Some(prev) = model.prev.filter(|&prev| model.saves[prev].intact) {
+                let mut idx = hint.idx;
+                loop {
+                    prop_assert_ne!(model.slots[idx], Some(prev), "overwrote previous savegame");
+                    idx = (idx + 1) % SLOT_COUNT;
+                    if idx == next {
+                        break;
+                    }
+                }
+            }
+
+            // Savegames written after a reset may have other laps than the
+            // slots around them, unless nothing was found
+            model.clean &= !model.detached || model.scan().is_none();
+            model.detached = false;
+            model.append::<F>(
+                Save {
+                    idx: hint.idx,
+                    chksum: hint.chksum,
+                    prev,
+                    data,
+                    intact: true,
+                },
+                next,
+            );
+        }
+
+        check_scan(&mut storage, &mut model)
+    }
+
+    proptest! {
+        #[test]
+        fn prop_model_at24cxx_16x8(ops in vec(op(max_len::<MockFlash<128>, 16, 8>()), 1..64)) {
+            check_model::<_, 16, 8>(MockFlash::<128>::new(), &ops)?;
+        }
+
+        #[test]
+        fn prop_model_at24cxx_24x5(ops in vec(op(max_len::<MockFlash<120>, 24, 5>()), 1..64)) {
+            check_model::<_, 24, 5>(MockFlash::<120>::new(), &ops)?;
+        }
+
+        #[test]
+        fn prop_model_at24cxx_64x16(ops in vec(op(max_len::<MockFlash<1024>, 64, 16>()), 1..64)) {
+            check_model::<_, 64, 16>(MockFlash::<1024>::new(), &ops)?;
+        }
+
+        #[test]
+        fn prop_model_at24cxx_16x2(ops in vec(op(max_len::<MockFlash<32>, 16, 2>()), 1..64)) {
+            check_model::<_, 16, 2>(MockFlash::<32>::new(), &ops)?;
+        }
+
+        #[test]
+        fn prop_model_zeroed_32x6(ops in vec(op(max_len::<MockFlash<192, 0x00>, 32, 6>()), 1..64)) {
+            check_model::<_, 32, 6>(MockFlash::<192, 0x00>::new(), &ops)?;
+        }
+
+        #[test]
+        fn prop_model_w25qxx_16x8(ops in vec(op(max_len::<SectorMockFlash<16, 8>, 16, 8>()), 1..64)) {
+            check_model::<_, 16, 8>(SectorMockFlash::<16, 8>::new(), &ops)?;
+        }
+
+        #[test]
+        fn prop_model_packed_16x16(ops in vec(op(max_len::<SectorMockFlash<64, 4>, 16, 16>()), 1..64)) {
+            check_model::<_, 16, 16>(SectorMockFlash::<64, 4>::new(), &ops)?;
+        }
+
+        #[test]
+        fn prop_model_packed_32x8(ops in vec(op(max_len::<SectorMockFlash<128, 2>, 32, 8>()), 1..64)) {
+            check_model::<_, 32, 8>(SectorMockFlash::<128, 2>::new(), &ops)?;
+        }
+
+        #[test]
+        fn prop_model_aligned_32x8(ops in vec(op(max_len::<AlignedMockFlash<8, 64, 4>, 32, 8>()), 1..64)) {
+            check_model::<_, 32, 8>(AlignedMockFlash::<8, 64, 4>::new(), &ops)?;
+        }
+
+        #[test]
+        fn prop_model_aligned_16x12(ops in vec(op(max_len::<AlignedMockFlash<4, 32, 6>, 16, 12>()), 1..64)) {
+            check_model::<_, 16, 12>(AlignedMockFlash::<4, 32, 6>::new(), &ops)?;
+        }
+    }
+
+    /// Bytes changed by [`CrashFlash`] at once, with their new value
+    #[derive(Clone, Copy, Debug)]
+    struct Step {
+        addr: usize,
+        len: usize,
+        value: u8,
+    }
+
+    /// Flash recording every byte it changes, to replay power losses
+    ///
+    /// Writes clear bits like NOR flash if `ERASE_SIZE` is larger than a byte,
+    /// and overwrite bytes like EEPROM or FRAM otherwise. Sector erases are
+    /// recorded as a single step, an interrupted erase is assumed to leave the
+    /// sector either unchanged or erased.
+    struct CrashFlash<const SIZE: usize, const ERASE_SIZE: usize, const NEEDS_ERASE: bool = true> {
+        data: [u8; SIZE],
+        steps: Vec<Step>,
+    }
+
+    impl<const SIZE: usize, const ERASE_SIZE: usize, const NEEDS_ERASE: bool>
+        CrashFlash<SIZE, ERASE_SIZE, NEEDS_ERASE>
+    {
+        const fn new(data: [u8; SIZE]) -> Self {
+            Self {
+                data,
+                steps: Vec::new(),
+            }
+        }
+
+        fn set(&mut self, addr: usize, len: usize, value: u8) {
+            self.steps.push(Step { addr, len, value });
+            self.data[addr..addr + len].fill(value);
+        }
+    }
+
+    impl<const SIZE: usize, const ERASE_SIZE: usize, const NEEDS_ERASE: bool> Flash
+        for CrashFlash<SIZE, ERASE_SIZE, NEEDS_ERASE>
+    {
+        type Error = Infallible;
+
+        const ERASE_SIZE: usize = ERASE_SIZE;
+        const NEEDS_ERASE: bool = NEEDS_ERASE;
+
+        fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+            let addr = addr as usize;
+            buf.copy_from_slice(&self.data[addr..addr + buf.len()]);
+            Ok(())
+        }
+
+        fn write(&mut self, addr: u32, data: &mut [u8]) -> Result<(), Self::Error> {
+            for (addr, &byte) in (addr as usize..).zip(data.iter()) {
+                let value = if ERASE_SIZE > 1 {
+                    self.data[addr] & byte
+                } else {
+                    byte
+                };
+                self.set(addr, 1, value);
+            }
+            Ok(())
+        }
+
+        fn erase(&mut self, addr: u32) -> Result<(), Self::Error> {
+            self.set(addr as usize - addr as usize % ERASE_SIZE, ERASE_SIZE, 0xFF);
+            Ok(())
+        }
+    }
+
+    /// Savegame appended after recovering from a power loss, spanning slots
+    const AFTER_POWER_LOSS: &[u8] = b"appended after power loss";
+
+    /// Savegame found after a power loss, and found by both scans after
+    /// appending [`AFTER_POWER_LOSS`]
+    type Recovered = (Option<Vec<u8>>, [Option<Vec<u8>>; 2]);
+
+    /// Scan and read a flash image after a power loss, with both scans
+    ///
+    /// Returns the savegame found by each scan, and what both scans find after
+    /// [`AFTER_POWER_LOSS`] was appended next.
+    fn recover<
+        const SIZE: usize,
+        const ERASE_SIZE: usize,
+        const NEEDS_ERASE: bool,
+        const SLOT_SIZE: usize,
+        const SLOT_COUNT: usize,
+    >(
+        image: [u8; SIZE],
+    ) -> [Recovered; 2] {
+        let scan = |image, fast| {
+            let flash = CrashFlash::<SIZE, ERASE_SIZE, NEEDS_ERASE>::new(image);
+            let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash);
+            let scan = if fast {
+                storage.scan_fast()
+            } else {
+                storage.scan()
+            };
+            let Ok(slot) = scan;
+            let mut buf = [0u8; SIZE];
+            let data = slot.and_then(|slot| {
+                let Ok(data) = storage.read(slot.idx, &mut buf);
+                data.map(|data| data.to_vec())
+            });
+            (storage, data)
+        };
+        [false, true].map(|fast| {
+            let (mut storage, found) = scan(image, fast);
+            let Ok(()) = storage.append(AFTER_POWER_LOSS);
+            let image = storage.flash.data;
+            (found, [false, true].map(|fast| scan(image, fast).1))
+        })
+    }
+
+    /// Interrupt `op` at every byte it changes, with the byte in every state
+    /// between its old and new value, and at every sector erase
+    ///
+    /// `prior` savegames are appended first. After each power loss, a scan has
+    /// to find one of `allowed`, and one of `done` once `op` completed. The
+    /// next savegame appended after the power loss has to be found intact.
+    fn check_power_loss<
+        const SIZE: usize,
+        const ERASE_SIZE: usize,
+        const NEEDS_ERASE: bool,
+        const SLOT_SIZE: usize,
+        const SLOT_COUNT: usize,
+    >(
+        prior: usize,
+        allowed: &[Option<Vec<u8>>],
+        done: &[Option<Vec<u8>>],
+        op: impl FnOnce(&mut Storage<CrashFlash<SIZE, ERASE_SIZE, NEEDS_ERASE>, SLOT_SIZE, SLOT_COUNT>),
+    ) {
+        let flash = CrashFlash::<SIZE, ERASE_SIZE, NEEDS_ERASE>::new([0xFF; SIZE]);
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash);
+        for save in history(prior) {
+            let Ok(()) = storage.append(&save);
+        }
+
+        let mut image = storage.flash.data;
+        storage.flash.steps.clear();
+        op(&mut storage);
+        let steps = core::mem::take(&mut storage.flash.steps);
+
+        let after = Some(AFTER_POWER_LOSS.to_vec());
+        let check = |image, num: usize, allowed: &[Option<Vec<u8>>]| {
+            let recovered = recover::<SIZE, ERASE_SIZE, NEEDS_ERASE, SLOT_SIZE, SLOT_COUNT>(image);
+            for (found, appended) in recovered {
+                assert!(
+                    allowed.contains(&found),
+                    "power loss at step {num} of {} after {prior} savegames: found {found:?}",
+                    steps.len(),
+                );
+                assert_eq!(
+                    appended,
+                    [after.clone(), after.clone()],
+                    "append after power loss at step {num} of {} after {prior} savegames",
+                    steps.len(),
+                );
+            }
+        };
+        for (num, step) in steps.iter().enumerate() {
+            if step.len > 1 {
+                check(image, num, allowed);
+                image[step.addr..step.addr + step.len].fill(step.value);
+                continue;
+            }
+
+            // Every subset of the changing bits, the complete change is the
+            // state before the next step
+            let old = image[step.addr];
+            let changed = old ^ step.value;
+            let mut bits = 0u8;
+            while bits != changed {
+                image[step.addr] = old ^ bits;
+                check(image, num, allowed);
+                bits = bits.wrapping_sub(changed) & changed;
+            }
+            image[step.addr] = step.value;
+        }
+
+        // The completed operation
+        check(image, steps.len(), done);
+    }
+
+    /// The savegames appended before the interrupted operation
+    fn history(prior: usize) -> Vec<Vec<u8>> {
+        (0..prior).map(|num| [num as u8; 3].to_vec()).collect()
+    }
+
+    fn test_power_loss<
+        const SIZE: usize,
+        const ERASE_SIZE: usize,
+        const NEEDS_ERASE: bool,
+        const SLOT_SIZE: usize,
+        const SLOT_COUNT: usize,
+    >() {
+        for prior in 0..=SLOT_COUNT + 1 {
+            let history = history(prior);
+            let previous = history.last().cloned();
+
+            // Single and multi-slot savegames, from every position
+            for len in [0, 3, 4, 5, 19, 30] {
+                let data: Vec<u8> = (0..len)
+                    .map(|i| (i as u8).wrapping_mul(0x35) ^ 0xA5)
+                    .collect();
+                let new = Some(data.clone());
+                check_power_loss::<SIZE, ERASE_SIZE, NEEDS_ERASE, SLOT_SIZE, SLOT_COUNT>(
+                    prior,
+                    &[previous.clone(), new.clone()],
+                    &[new],
+                    |storage| {
+                        let Ok(()) = storage.append(&data);
+                    },
+                );
+            }
+
+            let data = [0x5A, 0x0F, 0xF0, 0x00];
+            let new = Some(data.to_vec());
+            check_power_loss::<SIZE, ERASE_SIZE, NEEDS_ERASE, SLOT_SIZE, SLOT_COUNT>(
+                prior,
+                &[previous.clone(), new.clone()],
+                &[new],
+                |storage| {
+                    let Ok(()) = storage.append_static(&data);
+                },
+            );
+
+            // Wiping everything may uncover any older savegame on the way
+            let committed: Vec<_> = history.iter().cloned().map(Some).chain([None]).collect();
+            check_power_loss::<SIZE, ERASE_SIZE, NEEDS_ERASE, SLOT_SIZE, SLOT_COUNT>(
+                prior,
+                &committed,
+                &[None],
+                |storage| {
+                    let Ok(()) = storage.erase_all();
+                },
+            );
+            check_power_loss::<SIZE, ERASE_SIZE, NEEDS_ERASE, SLOT_SIZE, SLOT_COUNT>(
+                prior,
+                &committed,
+                &[None],
+                |storage| {
+                    let Ok(()) = storage.secure_erase_all();
+                },
+            );
+
+            // The latest savegame is destroyed, older ones may be found again
+            let older: Vec<_> = history
+                .iter()
+                .take(prior.saturating_sub(1))
+                .cloned()
+                .map(Some)
+                .chain([None])
+                .collect();
+            let allowed: Vec<_> = older.iter().cloned().chain([previous]).collect();
+            // Sectors shared with other savegames aren't erased
+            let done = if ERASE_SIZE > SLOT_SIZE {
+                &allowed
+            } else {
+                &older
+            };
+            check_power_loss::<SIZE, ERASE_SIZE, NEEDS_ERASE, SLOT_SIZE, SLOT_COUNT>(
+                prior,
+                &allowed,
+                done,
+                |storage| {
+                    let head = storage.hint().map_or(0, |hint| hint.idx);
+                    let Ok(_) = storage.secure_erase(head);
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn test_power_loss_at24cxx() {
+        test_power_loss::<64, 1, true, 16, 4>();
+    }
+
+    #[test]
+    fn test_power_loss_fram() {
+        test_power_loss::<64, 1, false, 16, 4>();
+    }
+
+    #[test]
+    fn test_power_loss_w25qxx() {
+        test_power_loss::<64, 16, true, 16, 4>();
+    }
+
+    #[test]
+    fn test_power_loss_packed() {
+        test_power_loss::<128, 32, true, 16, 8>();
+    }
+
+    fn test_append_three_times_then_scan<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        let data = *b"first";
+        storage.append(&data);
+        let data = *b"second";
+        storage.append(&data);
+        let data = *b"third";
+        storage.append(&data);
+
+        let slot = storage.scan().unwrap();
+        assert_eq!(
+            slot,
+            Some(Slot {
+                idx: 2,
+                chksum: Chksum::hash(
+                    Chksum::hash(Chksum::hash(Chksum::zero(), b"first"), b"second",),
+                    b"third",
+                ),
+                len: 5,
+                prev: Chksum::hash(Chksum::hash(Chksum::zero(), b"first"), b"second",),
+                flags: 0,
+            })
+        );
+        assert_eq!(storage.idx, 3);
+        assert_eq!(
+            storage.prev,
+            Chksum::hash(
+                Chksum::hash(Chksum::hash(Chksum::zero(), b"first"), b"second",),
+                b"third",
+            )
+        );
+    }
+
+    #[test]
+    fn test_at24cxx_append_three_times_then_scan() {
+        let mut storage = mock_storage();
+        test_append_three_times_then_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_zeroed_append_three_times_then_scan() {
+        let mut storage = mock_zeroed_storage();
+        test_append_three_times_then_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_w25qxx_append_three_times_then_scan() {
+        let mut storage = mock_sector_storage();
+        test_append_three_times_then_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_packed_append_three_times_then_scan() {
+        let mut storage = mock_packed_storage();
+        test_append_three_times_then_scan(&mut storage);
+    }
+
+    fn test_append_static_three_times_then_scan<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        let data = *b"first";
+        storage.append_static(&data);
+        let data = *b"second";
+        storage.append_static(&data);
+        let data = *b"third";
+        storage.append_static(&data);
+
+        let slot = storage.scan().unwrap();
+        assert_eq!(
+            slot,
+            Some(Slot {
+                idx: 2,
+                chksum: Chksum::hash(
+                    Chksum::hash(Chksum::hash(Chksum::zero(), b"first"), b"second",),
+                    b"third",
+                ),
+                len: 5,
+                prev: Chksum::hash(Chksum::hash(Chksum::zero(), b"first"), b"second",),
+                flags: 0,
+            })
+        );
+        assert_eq!(storage.idx, 3);
+        assert_eq!(
+            storage.prev,
+            Chksum::hash(
+                Chksum::hash(Chksum::hash(Chksum::zero(), b"first"), b"second",),
+                b"third",
+            )
+        );
+    }
+
+    #[test]
+    fn test_at24cxx_append_static_three_times_then_scan() {
+        let mut storage = mock_storage();
+        test_append_static_three_times_then_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_zeroed_append_static_three_times_then_scan() {
+        let mut storage = mock_zeroed_storage();
+        test_append_static_three_times_then_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_w25qxx_append_static_three_times_then_scan() {
+        let mut storage = mock_sector_storage();
+        test_append_static_three_times_then_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_packed_append_static_three_times_then_scan() {
+        let mut storage = mock_packed_storage();
+        test_append_static_three_times_then_scan(&mut storage);
+    }
+
+    #[test]
+    fn test_at24cxx_static_non_static_append_equality() {
+        let mut storage_non_static_writes = mock_storage();
+        test_append_three_times_then_scan(&mut storage_non_static_writes);
+        let mut storage_static_writes = mock_storage();
+        test_append_static_three_times_then_scan(&mut storage_static_writes);
+        assert_eq!(storage_non_static_writes.flash, storage_static_writes.flash);
+    }
+
+    #[test]
+    fn test_w25qxx_static_non_static_append_equality() {
+        let mut storage_non_static_writes = mock_sector_storage();
+        test_append_three_times_then_scan(&mut storage_non_static_writes);
+        let mut storage_static_writes = mock_sector_storage();
+        test_append_static_three_times_then_scan(&mut storage_static_writes);
+        assert_eq!(storage_non_static_writes.flash, storage_static_writes.flash);
+    }
 
-        let mut addr = slot_addr.saturating_add(Slot::HEADER_SIZE as u32);
-        let mut remaining_space = SLOT_SIZE - Slot::HEADER_SIZE;
+    fn test_storage_erase_all<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        for save in 0..5u8 {
+            let data = [save; SLOT_SIZE];
+            storage.append(&data);
+        }
 
-        loop {
-            let write_size = remaining_space.min(data.len());
-            let (to_write, remaining) = data.split_at_mut(write_size);
-            self.flash.write(addr, to_write)?;
-            data = remaining;
-            idx = idx.saturating_add(1) % SLOT_COUNT;
+        let Ok(()) = storage.erase_all();
+        let Ok(slot) = storage.scan();
+        assert_eq!(slot, None);
 
-            // erase first byte of next slot, but only if more data remains
-            if data.is_empty() {
-                break;
-            }
+        // Storage starts over from the first slot
+        let data = *b"fresh";
+        storage.append(&data);
+        let Ok(slot) = storage.scan();
+        assert_eq!(slot.map(|slot| slot.idx), Some(0));
+    }
 
-            addr = self.addr(idx);
-            self.flash.erase(addr)?;
+    #[test]
+    fn test_at24cxx_storage_erase_all() {
+        let mut storage = mock_storage();
+        test_storage_erase_all(&mut storage);
+    }
 
-            addr = addr.saturating_add(1);
-            remaining_space = SLOT_SIZE - 1;
-        }
+    #[test]
+    fn test_zeroed_storage_erase_all() {
+        let mut storage = mock_zeroed_storage();
+        test_storage_erase_all(&mut storage);
+    }
 
-        // Write header last, to finalize the slot
-        // The last field is `prev`, marking the previous slot as outdated
-        let mut bytes = slot.to_bytes();
-        self.flash.write(slot_addr, &mut bytes)?;
+    #[test]
+    fn test_w25qxx_storage_erase_all() {
+        let mut storage = mock_sector_storage();
+        test_storage_erase_all(&mut storage);
+    }
 
-        Ok((idx, slot.chksum))
+    #[test]
+    fn test_packed_storage_erase_all() {
+        let mut storage = mock_packed_storage();
+        test_storage_erase_all(&mut storage);
     }
 
-    /// Write a static-sized savegame directly into a single slot
-    ///
-    /// This is a more lightweight write operation for fixed-size data that fits
-    /// within a single slot (excluding the header). The size must not exceed
-    /// `SLOT_SIZE - Slot::HEADER_SIZE`.
-    pub fn write_static<const SIZE: usize>(
-        &mut self,
-        mut idx: usize,
-        prev: Chksum,
-        data: &mut [u8; SIZE],
-    ) -> Result<(usize, Chksum), F::Error> {
-        // Sanity check
-        const {
-            let space_available = SLOT_SIZE
-                .checked_sub(Slot::HEADER_SIZE)
-                .expect("Invalid SLOT_SIZE, Slot::HEADER_SIZE doesn't fit");
-            assert!(SIZE <= space_available);
+    #[test]
+    fn test_measured_no_erase_storage_erase_all() {
+        let mut storage = mock_measured_no_erase_storage();
+        test_storage_erase_all(&mut storage);
+    }
+
+    const SECRET: u8 = 0x5A;
+
+    fn assert_no_secret<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+        slots: core::ops::Range<usize>,
+    ) {
+        for idx in slots {
+            let mut buf = [0u8; SLOT_SIZE];
+            let Ok(()) = storage.flash.read(storage.addr(idx), &mut buf);
+            assert_eq!(buf, [F::ERASED; SLOT_SIZE], "slot {idx} not wiped");
         }
+    }
 
-        // Prepare slot header
-        let slot = Slot::create(idx, prev, data);
-        let slot_addr = self.addr(idx);
-        self.flash.erase(slot_addr)?;
+    fn test_storage_secure_erase<F: Flash<Error = Infallible>>(
+        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
+    ) {
+        // Spans three slots
+        let data = [SECRET; SLOT_SIZE * 2];
+        storage.append(&data);
+        let data = *b"keep";
+        storage.append(&data);
 
-        // Write data directly after header
-        let addr = slot_addr.saturating_add(Slot::HEADER_SIZE as u32);
-        self.flash.write(addr, data)?;
-        idx = idx.saturating_add(1) % SLOT_COUNT;
+        // A sector shared with the other savegame isn't erased
+        let Ok(erased) = storage.secure_erase(0);
+        assert_eq!(erased, F::ERASE_SIZE <= SLOT_SIZE);
+        if erased {
+            assert_no_secret(storage, 0..3);
+        }
 
-        // Write header last, to finalize the slot
-        // The last field is `prev`, marking the previous slot as outdated
-        let mut bytes = slot.to_bytes();
-        self.flash.write(slot_addr, &mut bytes)?;
+        // Slots of other savegames are kept
+        let mut buf = [0u8; 8];
+        let Ok(slice) = storage.read(3, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&b"keep"[..]));
 
-        Ok((idx, slot.chksum))
+        // Continuation slots are found without a valid header
+        let data = [SECRET; SLOT_SIZE * 2];
+        storage.append(&data);
+        let Ok(()) = storage.erase(4);
+        let Ok(true) = storage.secure_erase(4) else {
+            panic!("savegame not erased");
+        };
+        assert_no_secret(storage, 4..7);
     }
 
-    /// Append a new savegame at the next free slot
-    ///
-    /// The new savegame indicates it's an update to the previous savegame,
-    /// when fully written the scanner should find it as the most recent savegame.
-    pub fn append(&mut self, data: &mut [u8]) -> Result<(), F::Error> {
-        let (idx, chksum) = self.write(self.idx, self.prev, data)?;
-        self.idx = idx;
-        self.prev = chksum;
-        Ok(())
+    #[test]
+    fn test_at24cxx_storage_secure_erase() {
+        let mut storage = mock_storage();
+        test_storage_secure_erase(&mut storage);
     }
 
-    /// Append a static-sized savegame into the next free slot
-    ///
-    /// This is a more lightweight write operation for fixed-size data that fits
-    /// within a single slot (excluding the header). The size must not exceed
-    /// `SLOT_SIZE - Slot::HEADER_SIZE`.
-    pub fn append_static<const SIZE: usize>(
-        &mut self,
-        data: &mut [u8; SIZE],
-    ) -> Result<(), F::Error> {
-        let (idx, chksum) = self.write_static(self.idx, self.prev, data)?;
-        self.idx = idx;
-        self.prev = chksum;
-        Ok(())
+    #[test]
+    fn test_zeroed_storage_secure_erase() {
+        let mut storage = mock_zeroed_storage();
+        test_storage_secure_erase(&mut storage);
     }
 
-    /// Reset internal state to initial values
-    ///
-    /// This does not erase any data, but causes the next write to start at slot 0
-    /// with a zeroed previous checksum.
-    pub const fn reset(&mut self) {
-        self.idx = 0;
-        self.prev = Chksum::zero();
+    #[test]
+    fn test_w25qxx_storage_secure_erase() {
+        let mut storage = mock_sector_storage();
+        test_storage_secure_erase(&mut storage);
     }
 
-    /// Consume the storage manager and return the underlying flash device
-    ///
-    /// This can be used to retrieve the flash device after all storage operations
-    /// are complete.
-    pub fn into_inner(self) -> F {
-        self.flash
+    #[test]
+    fn test_packed_storage_secure_erase() {
+        let mut storage = mock_packed_storage();
+        test_storage_secure_erase(&mut storage);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::mock::{MeasuredMockFlash, MeasuredStats, MockFlash, SectorMockFlash};
-    use core::convert::Infallible;
-
-    const SLOT_SIZE: usize = 64;
-    const SLOT_COUNT: usize = 8;
-    const SIZE: usize = SLOT_SIZE * SLOT_COUNT;
 
-    const fn mock_storage() -> Storage<MockFlash<SIZE>, SLOT_SIZE, SLOT_COUNT> {
-        let flash = MockFlash::<SIZE>::new();
-        Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash)
+    #[test]
+    fn test_measured_no_erase_storage_secure_erase() {
+        let mut storage = mock_measured_no_erase_storage();
+        test_storage_secure_erase(&mut storage);
     }
 
-    const fn mock_sector_storage()
-    -> Storage<SectorMockFlash<SLOT_SIZE, SLOT_COUNT>, SLOT_SIZE, SLOT_COUNT> {
-        let flash = SectorMockFlash::<SLOT_SIZE, SLOT_COUNT>::new();
-        Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash)
-    }
+    #[test]
+    fn test_packed_storage_secure_erase_keeps_latest() {
+        let mut storage = mock_packed_storage();
+        // Both savegames fill a sector each
+        let data = [SECRET; SLOT_SIZE * 3];
+        storage.append(&data);
+        let data = [0x42; SLOT_SIZE * 3];
+        storage.append(&data);
 
-    fn mock_measured_storage() -> Storage<MeasuredMockFlash<SIZE>, SLOT_SIZE, SLOT_COUNT> {
-        let flash = MeasuredMockFlash::<SIZE>::new();
-        Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash)
+        let Ok(true) = storage.secure_erase(0) else {
+            panic!("savegame not erased");
+        };
+        assert_no_secret(&mut storage, 0..4);
+
+        let Ok(slot) = storage.scan();
+        assert_eq!(slot.map(|slot| slot.idx), Some(4));
+        let mut buf = [0u8; SLOT_SIZE * 3];
+        let Ok(slice) = storage.read(4, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&data[..]));
     }
 
-    fn test_storage_empty_scan<F: Flash<Error = Infallible>>(
+    fn test_storage_secure_erase_all<F: Flash<Error = Infallible>>(
         storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
     ) {
+        for _ in 0..5 {
+            let data = [SECRET; SLOT_SIZE];
+            storage.append(&data);
+        }
+
+        let Ok(()) = storage.secure_erase_all();
+        assert_no_secret(storage, 0..SLOT_COUNT);
         let Ok(slot) = storage.scan();
         assert_eq!(slot, None);
     }
 
     #[test]
-    fn test_at24cxx_storage_empty_scan() {
+    fn test_at24cxx_storage_secure_erase_all() {
         let mut storage = mock_storage();
-        test_storage_empty_scan(&mut storage);
+        test_storage_secure_erase_all(&mut storage);
     }
 
     #[test]
-    fn test_w25qxx_storage_empty_scan() {
+    fn test_zeroed_storage_secure_erase_all() {
+        let mut storage = mock_zeroed_storage();
+        test_storage_secure_erase_all(&mut storage);
+    }
+
+    #[test]
+    fn test_w25qxx_storage_secure_erase_all() {
         let mut storage = mock_sector_storage();
-        test_storage_empty_scan(&mut storage);
+        test_storage_secure_erase_all(&mut storage);
     }
 
     #[test]
-    fn test_measured_storage_empty_scan() {
+    fn test_packed_storage_secure_erase_all() {
+        let mut storage = mock_packed_storage();
+        test_storage_secure_erase_all(&mut storage);
+    }
+
+    #[test]
+    fn test_measured_no_erase_storage_secure_erase_all() {
+        let mut storage = mock_measured_no_erase_storage();
+        test_storage_secure_erase_all(&mut storage);
+    }
+
+    #[test]
+    fn test_measured_storage_secure_erase_all() {
         let mut storage = mock_measured_storage();
-        test_storage_empty_scan(&mut storage);
+        let data = [SECRET; SLOT_SIZE];
+        storage.append(&data);
+
+        // EEPROM is overwritten in chunks instead of erased byte by byte
+        storage.flash.stats = MeasuredStats::default();
+        let Ok(()) = storage.secure_erase_all();
         assert_eq!(
             storage.flash.stats,
             MeasuredStats {
-                read: 8,
-                write: 0,
+                read: 0,
+                write: SIZE,
                 erase: 0,
             }
         );
+        assert_no_secret(&mut storage, 0..SLOT_COUNT);
     }
 
     #[test]
-    fn test_storage_write() {
-        let mut storage = mock_storage();
+    fn test_erase_all_keeps_outside_region() {
+        // Storage covers the first two of four sectors
+        let mut flash = SectorMockFlash::<PACKED_SECTOR_SIZE, 4>::new();
+        let mut outside = [0xA5; PACKED_SECTOR_SIZE];
+        let Ok(()) = flash.write(SIZE as u32, &mut outside);
 
-        let mut data = *b"hello world";
-        storage.append(&mut data);
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash);
+        let data = *b"savegame";
+        storage.append(&data);
+        let Ok(()) = storage.erase_all();
+        let Ok(slot) = storage.scan();
+        assert_eq!(slot, None);
 
-        let mut buf = [0u8; Slot::HEADER_SIZE];
-        storage.flash.read(0, &mut buf);
-        let slot = Slot::from_bytes(0, buf);
-        assert_eq!(
-            slot,
-            Slot {
-                idx: 0,
-                chksum: Chksum::hash(Chksum::zero(), &data),
-                len: data.len() as u32,
-                prev: Chksum::zero(),
-            }
-        );
+        let mut flash = storage.into_inner();
+        let mut buf = [0u8; PACKED_SECTOR_SIZE];
+        let Ok(()) = flash.read(SIZE as u32, &mut buf);
+        assert_eq!(buf, outside);
     }
 
     #[test]
-    fn test_storage_write_static() {
-        let mut storage = mock_storage();
+    fn test_packed_keeps_sector_neighbours() {
+        let mut storage = mock_packed_storage();
+        let data = *b"first";
+        storage.append(&data);
+        let data = *b"second";
+        storage.append(&data);
 
-        let mut data = *b"hello world";
-        storage.append_static(&mut data);
+        // Writing the second slot must not erase the first slot in the same sector
+        let mut buf = [0u8; 32];
+        let Ok(slice) = storage.read(0, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&b"first"[..]));
+        let Ok(slice) = storage.read(1, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&b"second"[..]));
+    }
 
-        let mut buf = [0u8; Slot::HEADER_SIZE];
-        storage.flash.read(0, &mut buf);
-        let slot = Slot::from_bytes(0, buf);
-        assert_eq!(
-            slot,
-            Slot {
-                idx: 0,
-                chksum: Chksum::hash(Chksum::zero(), &data),
-                len: data.len() as u32,
-                prev: Chksum::zero(),
-            }
-        );
+    fn test_aligned_storage<const WRITE_SIZE: usize>() {
+        let flash = AlignedMockFlash::<WRITE_SIZE, { SLOT_SIZE * 2 }, { SLOT_COUNT / 2 }>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(flash);
+
+        for num in 0..(SLOT_COUNT as u8 * 3 + 2) {
+            // Vary the length to cover partial units and multi-slot savegames
+            let data = [num; SLOT_SIZE * 2];
+            let len = (num as usize * 13) % data.len();
+            storage.append(&data[..len]).unwrap();
+
+            let slot = storage.scan().unwrap().unwrap();
+            let mut buf = [0u8; SLOT_SIZE * 2];
+            let slice = storage.read(slot.idx, &mut buf).unwrap();
+            assert_eq!(slice.map(|s| &*s), Some(&data[..len]));
+        }
+
+        let data = *b"static";
+        storage.append_static(&data).unwrap();
+        let slot = storage.scan().unwrap().unwrap();
+        let mut buf = [0u8; 6];
+        storage.read_static(slot.idx, &mut buf).unwrap();
+        assert_eq!(&buf, b"static");
     }
 
-    fn test_storage_write_scan<F: Flash<Error = Infallible>>(
-        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
-    ) {
-        let mut data = *b"hello world";
-        storage.append(&mut data);
+    #[test]
+    fn test_aligned_storage_write_size_4() {
+        test_aligned_storage::<4>();
+    }
 
-        let Ok(scan) = storage.scan();
-        assert_eq!(
-            scan,
-            Some(Slot {
-                idx: 0,
-                chksum: Chksum::hash(Chksum::zero(), &data),
-                len: data.len() as u32,
-                prev: Chksum::zero(),
-            })
-        );
+    #[test]
+    fn test_aligned_storage_write_size_8() {
+        test_aligned_storage::<8>();
     }
 
     #[test]
-    fn test_at24cxx_storage_write_scan() {
-        let mut storage = mock_storage();
-        test_storage_write_scan(&mut storage);
+    fn test_aligned_storage_write_size_32() {
+        test_aligned_storage::<32>();
     }
 
+    #[cfg(feature = "mac")]
+    const KEY: Key = Key::new([0x42; 32]);
+
+    #[cfg(feature = "mac")]
     #[test]
-    fn test_w25qxx_storage_write_scan() {
-        let mut storage = mock_sector_storage();
-        test_storage_write_scan(&mut storage);
+    fn test_mac_storage_write_read() {
+        let flash = MockFlash::<SIZE>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(flash, KEY);
+        let data = [0x11; SLOT_SIZE * 2];
+        let Ok(()) = storage.append(&data);
+        let data = *b"hello world";
+        let Ok(()) = storage.append(&data);
+
+        let Ok(Some(slot)) = storage.scan() else {
+            panic!("no savegame found");
+        };
+        assert_eq!(slot.idx, 3);
+        assert_eq!(slot.len, 11 + mac::TAG_SIZE as u32);
+
+        let mut buf = [0u8; SLOT_SIZE * 2];
+        let Ok(slice) = storage.read(3, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&b"hello world"[..]));
+        let Ok(slice) = storage.read(0, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&[0x11; SLOT_SIZE * 2][..]));
+
+        let mut buf = [0u8; 11];
+        let Ok(()) = storage.read_static(3, &mut buf);
+        assert_eq!(&buf, b"hello world");
     }
 
+    #[cfg(feature = "mac")]
     #[test]
-    fn test_measured_storage_write_scan() {
-        let mut storage = mock_measured_storage();
-        test_storage_write_scan(&mut storage);
-        assert_eq!(
-            storage.flash.stats,
-            MeasuredStats {
-                read: 19,
-                write: 23,
-                erase: 1,
-            }
+    fn test_mac_storage_vectored() {
+        let flash = MockFlash::<SIZE>::new();
+        let mut vectored = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(flash, KEY);
+        let flash = MockFlash::<SIZE>::new();
+        let mut contiguous = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(flash, KEY);
+        test_storage_write_vectored(&mut vectored, &mut contiguous);
+    }
+
+    #[cfg(feature = "mac")]
+    #[test]
+    fn test_mac_storage_tampered() {
+        let flash = MockFlash::<SIZE>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(flash, KEY);
+        let data = *b"first";
+        let Ok(()) = storage.append(&data);
+        let data = *b"100 coins";
+        let Ok(()) = storage.append(&data);
+
+        // Edit the data of the latest savegame
+        let addr = storage.addr(1) + Slot::HEADER_SIZE as u32;
+        let Ok(()) = storage.flash.write(addr, &mut [b'9'; 3]);
+
+        let mut buf = [0u8; 32];
+        let Ok(slice) = storage.read(1, &mut buf);
+        assert_eq!(slice, None);
+        let mut buf = [0u8; 9];
+        let Ok(()) = storage.read_static(1, &mut buf);
+        assert_eq!(buf, [0; 9]);
+
+        let Ok(slot) = storage.scan();
+        assert_eq!(slot.map(|slot| slot.idx), Some(0));
+    }
+
+    #[cfg(feature = "mac")]
+    #[test]
+    fn test_mac_storage_forged() {
+        let flash = MockFlash::<SIZE>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(flash, KEY);
+        let data = *b"100 coins";
+        let Ok(()) = storage.append(&data);
+
+        // Append a savegame with a valid checksum, but without the key
+        let mut forger = Storage::<_, SLOT_SIZE, SLOT_COUNT>::new(storage.into_inner());
+        let Ok(Some(_)) = forger.scan() else {
+            panic!("no savegame found");
+        };
+        let data = *b"999 coins";
+        let Ok(()) = forger.append(&data);
+
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(forger.into_inner(), KEY);
+        let Ok(slot) = storage.scan();
+        assert_eq!(slot.map(|slot| slot.idx), Some(0));
+
+        // Savegames can't be read with another key either
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(
+            storage.into_inner(),
+            Key::new([0x43; 32]),
         );
+        let Ok(slot) = storage.scan();
+        assert_eq!(slot, None);
+    }
+
+    #[cfg(feature = "mac")]
+    #[test]
+    fn test_mac_storage_corrupted_len() {
+        let flash = MeasuredMockFlash::<SIZE>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(flash, KEY);
+        let data = *b"first";
+        let Ok(()) = storage.append(&data);
+
+        // A header claiming more data than all slots hold isn't verified
+        let Ok(mut slot) = storage.read_header(0);
+        slot.len = Slot::MAX_LEN as u32;
+        let Ok(()) = storage.write_header(&slot);
+        storage.flash.stats = Default::default();
+
+        let Ok(slot) = storage.scan();
+        assert_eq!(slot, None);
+        assert!(storage.flash.stats.read < SIZE);
+    }
+
+    #[cfg(feature = "mac")]
+    #[test]
+    fn test_mac_aligned_storage() {
+        let flash = AlignedMockFlash::<8, { SLOT_SIZE * 2 }, { SLOT_COUNT / 2 }>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(flash, KEY);
+
+        for num in 0..(SLOT_COUNT as u8 * 3) {
+            // The MAC starts within a partial programming unit
+            let data = [num; SLOT_SIZE * 2];
+            let len = (num as usize * 13) % data.len();
+            storage.append(&data[..len]).unwrap();
+            storage.append_static(&[num; 4]).unwrap();
+
+            let slot = storage.scan().unwrap().unwrap();
+            let mut buf = [0u8; 4];
+            let slice = storage.read(slot.idx, &mut buf).unwrap();
+            assert_eq!(slice.map(|s| &*s), Some(&[num; 4][..]));
+        }
     }
 
-    fn test_storage_write_read<F: Flash<Error = Infallible>>(
-        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
-    ) {
-        let mut data = *b"hello world";
-        storage.append(&mut data);
+    #[cfg(feature = "encrypt")]
+    #[test]
+    fn test_encrypted_storage_vectored() {
+        let flash = MockFlash::<SIZE>::new();
+        let mut vectored = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_encryption(flash, KEY);
+        let flash = MockFlash::<SIZE>::new();
+        let mut contiguous = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_encryption(flash, KEY);
+        test_storage_write_vectored(&mut vectored, &mut contiguous);
+    }
 
-        let mut buf = [0u8; 1024];
+    #[cfg(feature = "encrypt")]
+    #[test]
+    fn test_encrypted_storage_write_read() {
+        let flash = MockFlash::<SIZE>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_encryption(flash, KEY);
+        let data = [b"password=hunter2".as_slice(), &[0x11; SLOT_SIZE]].concat();
+        let Ok(()) = storage.append(&data);
+        let data = *b"token=s3cr3t";
+        let Ok(()) = storage.append(&data);
+
+        // No plaintext at rest
+        let mut raw = [0u8; SIZE];
+        let Ok(()) = storage.flash.read(0, &mut raw);
+        assert!(!raw.windows(6).any(|w| w == b"hunter" || w == b"s3cr3t"));
+
+        let Ok(Some(slot)) = storage.scan() else {
+            panic!("no savegame found");
+        };
+        assert_eq!(slot.idx, 2);
+        assert_eq!(slot.len, 12 + encrypt::IV_SIZE as u32);
+
+        let mut buf = [0u8; SLOT_SIZE * 2];
+        let Ok(slice) = storage.read(2, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&b"token=s3cr3t"[..]));
         let Ok(slice) = storage.read(0, &mut buf);
+        assert_eq!(slice.map(|s| &s[..16]), Some(&b"password=hunter2"[..]));
 
-        assert_eq!(slice.map(|s| &*s), Some("hello world".as_bytes()));
+        let mut buf = [0u8; 12];
+        let Ok(()) = storage.read_static(2, &mut buf);
+        assert_eq!(&buf, b"token=s3cr3t");
     }
 
+    #[cfg(feature = "encrypt")]
     #[test]
-    fn test_at24cxx_storage_write_read() {
-        let mut storage = mock_storage();
-        test_storage_write_read(&mut storage);
+    fn test_encrypted_storage_tampered() {
+        let flash = MockFlash::<SIZE>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_encryption(flash, KEY);
+        let data = *b"first";
+        let Ok(()) = storage.append(&data);
+        let data = *b"100 coins";
+        let Ok(()) = storage.append(&data);
+
+        // Flip a bit of the ciphertext of the latest savegame
+        let addr = storage.addr(1) + (Slot::HEADER_SIZE + encrypt::IV_SIZE) as u32;
+        let mut byte = [0u8];
+        let Ok(()) = storage.flash.read(addr, &mut byte);
+        byte[0] ^= 1;
+        let Ok(()) = storage.flash.write(addr, &mut byte);
+
+        let mut buf = [0u8; 32];
+        let Ok(slice) = storage.read(1, &mut buf);
+        assert_eq!(slice, None);
+        assert_eq!(buf, [0; 32]);
+
+        let Ok(slot) = storage.scan();
+        assert_eq!(slot.map(|slot| slot.idx), Some(0));
+
+        // Savegames can't be read as authenticated only
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_key(storage.into_inner(), KEY);
+        let Ok(slot) = storage.scan();
+        assert_eq!(slot, None);
     }
 
+    #[cfg(feature = "encrypt")]
     #[test]
-    fn test_w25qxx_storage_write_read() {
-        let mut storage = mock_sector_storage();
-        test_storage_write_read(&mut storage);
+    fn test_encrypted_aligned_storage() {
+        let flash = AlignedMockFlash::<8, { SLOT_SIZE * 2 }, { SLOT_COUNT / 2 }>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_encryption(flash, KEY);
+
+        for num in 0..(SLOT_COUNT as u8 * 3) {
+            let data = [num; SLOT_SIZE * 2];
+            let len = (num as usize * 13) % data.len();
+            storage.append(&data[..len]).unwrap();
+
+            let slot = storage.scan().unwrap().unwrap();
+            let mut buf = [0u8; SLOT_SIZE * 2];
+            let slice = storage.read(slot.idx, &mut buf).unwrap();
+            assert_eq!(slice.map(|s| &*s), Some(&[num; SLOT_SIZE * 2][..len]));
+        }
     }
 
-    #[test]
-    fn test_measured_storage_write_read() {
-        let mut storage = mock_measured_storage();
-        test_storage_write_read(&mut storage);
-        assert_eq!(
-            storage.flash.stats,
-            MeasuredStats {
-                read: 23,
-                write: 23,
-                erase: 1,
-            }
-        );
+    /// Game state that is mostly zeros
+    #[cfg(feature = "compress")]
+    fn game_state() -> [u8; SLOT_SIZE * 4] {
+        let mut data = [0u8; SLOT_SIZE * 4];
+        data[..11].copy_from_slice(b"hello world");
+        data[100] = 42;
+        data[200..204].copy_from_slice(&1337u32.to_be_bytes());
+        data
     }
 
-    fn test_storage_write_wrap_around<F: Flash<Error = Infallible>>(
+    #[cfg(feature = "compress")]
+    fn test_storage_compressed<F: Flash<Error = Infallible>>(
         storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
     ) {
-        for num in 0..(SLOT_COUNT as u32 * 3 + 2) {
-            let mut buf = [0u8; 6];
-            num.to_be_bytes().iter().enumerate().for_each(|(i, b)| {
-                buf[i] = *b;
-            });
-            storage.append(&mut buf);
-        }
+        let data = game_state();
+        let mut scratch = [0u8; compress::max_size(SLOT_SIZE * 4)];
+        let Ok(()) = storage.append_compressed(&data, &mut scratch);
+        // Incompressible data is stored uncompressed
+        let small = *b"hello world";
+        let Ok(()) = storage.append_compressed(&small, &mut scratch);
 
-        let slot = storage.scan().unwrap().unwrap();
+        let Ok(Some(slot)) = storage.scan() else {
+            panic!("no savegame found");
+        };
         assert_eq!(slot.idx, 1);
-        assert_eq!(storage.idx, 2);
+        assert_eq!(slot.flags(), 0);
 
-        let mut buf = [0u8; 32];
-        let Ok(slice) = storage.read(slot.idx, &mut buf);
-        assert_eq!(slice, Some(&mut [0, 0, 0, 25, 0, 0][..]));
+        let mut buf = [0u8; SLOT_SIZE * 5];
+        let Ok(slice) = storage.read(1, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&small[..]));
+        let Ok(slice) = storage.read(0, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&data[..]));
+
+        // The buffer must hold the compressed and decompressed data
+        let mut buf = [0u8; SLOT_SIZE * 4];
+        let Ok(slice) = storage.read(0, &mut buf);
+        assert_eq!(slice, None);
     }
 
+    #[cfg(feature = "compress")]
     #[test]
-    fn test_at24cxx_storage_write_wrap_around() {
+    fn test_at24cxx_storage_compressed() {
         let mut storage = mock_storage();
-        test_storage_write_wrap_around(&mut storage);
+        test_storage_compressed(&mut storage);
     }
 
+    #[cfg(feature = "compress")]
     #[test]
-    fn test_w25qxx_storage_write_wrap_around() {
-        let mut storage = mock_sector_storage();
-        test_storage_write_wrap_around(&mut storage);
+    fn test_packed_storage_compressed() {
+        let mut storage = mock_packed_storage();
+        test_storage_compressed(&mut storage);
     }
 
+    #[cfg(feature = "compress")]
     #[test]
-    fn test_measured_storage_write_wrap_around() {
+    fn test_measured_storage_compressed() {
         let mut storage = mock_measured_storage();
-        test_storage_write_wrap_around(&mut storage);
+        let data = game_state();
+        let Ok(()) = storage.append(&data);
         assert_eq!(
             storage.flash.stats,
             MeasuredStats {
-                read: 114,
-                write: 468,
-                erase: 26,
-            }
-        );
-    }
-
-    fn test_storage_big_write<F: Flash<Error = Infallible>>(
-        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
-    ) {
-        let mut buf = [b'A'; SLOT_SIZE * 5];
-        storage.append(&mut buf);
-        let slot = storage.scan().unwrap().unwrap();
-        assert_eq!(
-            slot,
-            Slot {
-                idx: 0,
-                chksum: Chksum::hash(Chksum::zero(), &buf),
-                len: buf.len() as u32,
-                prev: Chksum::zero(),
+                read: 5,
+                write: 272,
+                erase: 5,
             }
         );
 
-        let mut buf2 = [0u8; 512];
-        let Ok(slice) = storage.read(slot.idx, &mut buf2);
-        assert_eq!(slice.map(|s| &*s), Some(&buf[..]));
-
-        let mut buf = [b'B'; SLOT_SIZE * 5];
-        storage.append(&mut buf);
-        let new_slot = storage.scan().unwrap().unwrap();
+        // The compressed savegame fits into a single slot
+        let mut storage = mock_measured_storage();
+        let mut scratch = [0u8; compress::max_size(SLOT_SIZE * 4)];
+        let Ok(()) = storage.append_compressed(&data, &mut scratch);
         assert_eq!(
-            new_slot,
-            Slot {
-                idx: 6,
-                chksum: Chksum::hash(slot.chksum, &buf),
-                len: buf.len() as u32,
-                prev: slot.chksum,
+            storage.flash.stats,
+            MeasuredStats {
+                read: 1,
+                write: 56,
+                erase: 1,
             }
         );
     }
 
+    #[cfg(all(feature = "compress", feature = "encrypt"))]
     #[test]
-    fn test_at24cxx_storage_big_write() {
-        let mut storage = mock_storage();
-        test_storage_big_write(&mut storage);
-    }
+    fn test_encrypted_storage_compressed() {
+        let flash = MockFlash::<SIZE>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_encryption(flash, KEY);
+        let data = game_state();
+        let mut scratch = [0u8; compress::max_size(SLOT_SIZE * 4)];
+        let Ok(()) = storage.append_compressed(&data, &mut scratch);
 
-    #[test]
-    fn test_w25qxx_storage_big_write() {
-        let mut storage = mock_sector_storage();
-        test_storage_big_write(&mut storage);
-    }
+        let Ok(Some(slot)) = storage.scan() else {
+            panic!("no savegame found");
+        };
+        assert_eq!(slot.flags(), Slot::FLAG_COMPRESSED);
 
-    #[test]
-    fn test_measured_storage_big_write() {
-        let mut storage = mock_measured_storage();
-        test_storage_big_write(&mut storage);
-        assert_eq!(
-            storage.flash.stats,
-            MeasuredStats {
-                read: 370,
-                write: 664,
-                erase: 12,
-            }
-        );
+        let mut buf = [0u8; SLOT_SIZE * 5];
+        let Ok(slice) = storage.read(0, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&data[..]));
+
+        // The flag can't be cleared to get the compressed plaintext
+        let mut header = [0u8; Slot::HEADER_SIZE];
+        let Ok(()) = storage.flash.read(0, &mut header);
+        header[4] = !0;
+        let Ok(()) = storage.flash.write(0, &mut header);
+        let Ok(slice) = storage.read(0, &mut buf);
+        assert_eq!(slice, None);
     }
 
-    fn test_append_after_scan<F: Flash<Error = Infallible>>(
+    #[cfg(feature = "delta")]
+    fn test_storage_delta<F: Flash<Error = Infallible>>(
         storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
     ) {
-        let mut big = [b'A'; SLOT_SIZE * 2];
-        storage.append(&mut big);
-        assert_eq!(storage.idx, 3);
-        storage.idx = 0;
+        let mut state = [0u8; SLOT_SIZE];
+        let mut base = state;
+        let mut scratch = [0u8; SLOT_SIZE];
+        let mut buf = [0u8; SLOT_SIZE];
 
-        storage.scan().unwrap();
-        assert_eq!(storage.idx, 3);
-        assert_eq!(storage.prev, Chksum::hash(Chksum::zero(), &big));
+        // Wrap around a few times, rescanning every other savegame
+        for i in 0..SLOT_COUNT * 3 {
+            state[i % SLOT_SIZE] = i as u8 + 1;
+            let data = state;
+            let idx = storage.idx;
+            assert_eq!(storage.append_delta(&data, &base, &mut scratch), Ok(true));
+            base = state;
+
+            if i % 2 == 0 {
+                let Ok(Some(_)) = storage.scan() else {
+                    panic!("no savegame found");
+                };
+            }
+            let Ok(slice) = storage.read(idx, &mut buf);
+            assert_eq!(slice.map(|s| &*s), Some(&state[..]));
+        }
     }
 
+    #[cfg(feature = "delta")]
     #[test]
-    fn test_at24cxx_append_after_scan() {
+    fn test_at24cxx_storage_delta() {
         let mut storage = mock_storage();
-        test_append_after_scan(&mut storage);
+        test_storage_delta(&mut storage);
     }
 
+    #[cfg(feature = "delta")]
     #[test]
-    fn test_w25qxx_append_after_scan() {
-        let mut storage = mock_sector_storage();
-        test_append_after_scan(&mut storage);
+    fn test_zeroed_storage_delta() {
+        let mut storage = mock_zeroed_storage();
+        test_storage_delta(&mut storage);
     }
 
+    #[cfg(feature = "delta")]
     #[test]
-    fn test_measured_append_after_scan() {
-        let mut storage = mock_measured_storage();
-        test_append_after_scan(&mut storage);
-        assert_eq!(
-            storage.flash.stats,
-            MeasuredStats {
-                read: 19,
-                write: 140,
-                erase: 3,
-            }
-        );
+    fn test_w25qxx_storage_delta() {
+        let mut storage = mock_sector_storage();
+        test_storage_delta(&mut storage);
     }
 
-    fn test_append_three_times_then_scan<F: Flash<Error = Infallible>>(
-        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
-    ) {
-        let mut data = *b"first";
-        storage.append(&mut data);
-        let mut data = *b"second";
-        storage.append(&mut data);
-        let mut data = *b"third";
-        storage.append(&mut data);
-
-        let slot = storage.scan().unwrap();
-        assert_eq!(
-            slot,
-            Some(Slot {
-                idx: 2,
-                chksum: Chksum::hash(
-                    Chksum::hash(Chksum::hash(Chksum::zero(), b"first"), b"second",),
-                    b"third",
-                ),
-                len: 5,
-                prev: Chksum::hash(Chksum::hash(Chksum::zero(), b"first"), b"second",),
-            })
-        );
-        assert_eq!(storage.idx, 3);
-        assert_eq!(
-            storage.prev,
-            Chksum::hash(
-                Chksum::hash(Chksum::hash(Chksum::zero(), b"first"), b"second",),
-                b"third",
-            )
-        );
+    #[cfg(feature = "delta")]
+    #[test]
+    fn test_packed_storage_delta() {
+        let mut storage = mock_packed_storage();
+        test_storage_delta(&mut storage);
     }
 
+    #[cfg(feature = "delta")]
     #[test]
-    fn test_at24cxx_append_three_times_then_scan() {
+    fn test_delta_checkpoints() {
         let mut storage = mock_storage();
-        test_append_three_times_then_scan(&mut storage);
+        let mut state = [0u8; 32];
+        let mut base = state;
+        let mut scratch = [0u8; 32];
+        storage.set_checkpoint_interval(2);
+
+        let mut flags = [0u8; 7];
+        for (i, flags) in flags.iter_mut().enumerate() {
+            state[i] = 1;
+            let data = state;
+            assert_eq!(storage.append_delta(&data, &base, &mut scratch), Ok(true));
+            base = state;
+
+            let Ok(Some(slot)) = storage.scan() else {
+                panic!("no savegame found");
+            };
+            *flags = slot.flags();
+        }
+        let delta = Slot::FLAG_DELTA;
+        assert_eq!(flags, [0, delta, delta, 0, delta, delta, 0]);
+
+        // Reading an older delta savegame reconstructs its state
+        let mut buf = [0u8; 32];
+        let Ok(slice) = storage.read(5, &mut buf);
+        let mut expected = [0u8; 32];
+        expected[..6].fill(1);
+        assert_eq!(slice.map(|s| &*s), Some(&expected[..]));
     }
 
+    #[cfg(feature = "delta")]
     #[test]
-    fn test_w25qxx_append_three_times_then_scan() {
-        let mut storage = mock_sector_storage();
-        test_append_three_times_then_scan(&mut storage);
+    fn test_delta_keeps_chain() {
+        // A full savegame takes three slots, so the chain leaves room for
+        // only two delta savegames before the next one would overwrite it
+        let mut storage = mock_storage();
+        let mut state = [0u8; SLOT_SIZE * 2];
+        let mut base = state;
+        let mut scratch = [0u8; SLOT_SIZE * 2];
+
+        for i in 0..4 {
+            state[i] = 1;
+            let data = state;
+            assert_eq!(storage.append_delta(&data, &base, &mut scratch), Ok(true));
+            base = state;
+        }
+        let Ok(Some(slot)) = storage.scan() else {
+            panic!("no savegame found");
+        };
+        assert_eq!(slot.idx, 5);
+        assert_eq!(slot.flags(), 0);
     }
 
-    fn test_append_static_three_times_then_scan<F: Flash<Error = Infallible>>(
-        storage: &mut Storage<F, SLOT_SIZE, SLOT_COUNT>,
-    ) {
-        let mut data = *b"first";
-        storage.append_static(&mut data);
-        let mut data = *b"second";
-        storage.append_static(&mut data);
-        let mut data = *b"third";
-        storage.append_static(&mut data);
+    #[cfg(feature = "delta")]
+    #[test]
+    fn test_delta_wrong_base() {
+        let mut storage = mock_storage();
+        let mut scratch = [0u8; 32];
+        let data = [0u8; 32];
+        assert_eq!(storage.append_delta(&data, &[], &mut scratch), Ok(true));
+        let mut data = [2u8; 32];
+        data[0] = 1;
+        assert_eq!(
+            storage.append_delta(&data, &[2u8; 32], &mut scratch),
+            Ok(false)
+        );
 
-        let slot = storage.scan().unwrap();
+        // Nothing was written, the latest savegame is still the first one
+        let Ok(Some(slot)) = storage.scan() else {
+            panic!("no savegame found");
+        };
+        assert_eq!(slot.idx, 0);
         assert_eq!(
-            slot,
-            Some(Slot {
-                idx: 2,
-                chksum: Chksum::hash(
-                    Chksum::hash(Chksum::hash(Chksum::zero(), b"first"), b"second",),
-                    b"third",
-                ),
-                len: 5,
-                prev: Chksum::hash(Chksum::hash(Chksum::zero(), b"first"), b"second",),
-            })
+            storage.append_delta(&data, &[2u8; 32], &mut scratch),
+            Ok(false)
         );
-        assert_eq!(storage.idx, 3);
         assert_eq!(
-            storage.prev,
-            Chksum::hash(
-                Chksum::hash(Chksum::hash(Chksum::zero(), b"first"), b"second",),
-                b"third",
-            )
+            storage.append_delta(&data, &[0u8; 32], &mut scratch),
+            Ok(true)
         );
-    }
 
-    #[test]
-    fn test_at24cxx_append_static_three_times_then_scan() {
-        let mut storage = mock_storage();
-        test_append_static_three_times_then_scan(&mut storage);
+        let mut buf = [0u8; 32];
+        let Ok(slice) = storage.read(1, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&data[..]));
     }
 
+    #[cfg(feature = "delta")]
     #[test]
-    fn test_w25qxx_append_static_three_times_then_scan() {
-        let mut storage = mock_sector_storage();
-        test_append_static_three_times_then_scan(&mut storage);
-    }
+    fn test_measured_storage_delta() {
+        let mut state = [0u8; SLOT_SIZE];
+        let mut storage = mock_measured_storage();
+        for i in 0..4 {
+            state[i] = 1;
+            let Ok(()) = storage.append(&state.clone());
+        }
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 8,
+                write: 308,
+                erase: 8,
+            }
+        );
 
-    #[test]
-    fn test_at24cxx_static_non_static_append_equality() {
-        let mut storage_non_static_writes = mock_storage();
-        test_append_three_times_then_scan(&mut storage_non_static_writes);
-        let mut storage_static_writes = mock_storage();
-        test_append_static_three_times_then_scan(&mut storage_static_writes);
-        assert_eq!(storage_non_static_writes.flash, storage_static_writes.flash);
+        // Only the first savegame is written in full
+        let mut state = [0u8; SLOT_SIZE];
+        let mut base = state;
+        let mut scratch = [0u8; SLOT_SIZE];
+        let mut storage = mock_measured_storage();
+        for i in 0..4 {
+            state[i] = 1;
+            assert_eq!(
+                storage.append_delta(&state.clone(), &base, &mut scratch),
+                Ok(true)
+            );
+            base = state;
+        }
+        assert_eq!(
+            storage.flash.stats,
+            MeasuredStats {
+                read: 5,
+                write: 152,
+                erase: 5,
+            }
+        );
     }
 
+    #[cfg(all(feature = "delta", feature = "encrypt"))]
     #[test]
-    fn test_w25qxx_static_non_static_append_equality() {
-        let mut storage_non_static_writes = mock_sector_storage();
-        test_append_three_times_then_scan(&mut storage_non_static_writes);
-        let mut storage_static_writes = mock_sector_storage();
-        test_append_static_three_times_then_scan(&mut storage_static_writes);
-        assert_eq!(storage_non_static_writes.flash, storage_static_writes.flash);
+    fn test_encrypted_storage_delta() {
+        let flash = MockFlash::<SIZE>::new();
+        let mut storage = Storage::<_, SLOT_SIZE, SLOT_COUNT>::with_encryption(flash, KEY);
+        let data = *b"password=hunter2";
+        assert_eq!(storage.append_delta(&data, &[], &mut [0u8; 16]), Ok(true));
+        let data = *b"password=hunter3";
+        assert_eq!(
+            storage.append_delta(&data, b"password=hunter2", &mut [0u8; 16]),
+            Ok(true)
+        );
+
+        let Ok(Some(slot)) = storage.scan() else {
+            panic!("no savegame found");
+        };
+        assert_eq!(slot.flags(), Slot::FLAG_DELTA);
+        let mut buf = [0u8; 16];
+        let Ok(slice) = storage.read(slot.idx, &mut buf);
+        assert_eq!(slice.map(|s| &*s), Some(&b"password=hunter3"[..]));
     }
 }
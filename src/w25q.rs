@@ -10,6 +10,9 @@ use core::fmt;
 use eh0::blocking::spi::Transfer;
 use eh0::digital::v2::OutputPin;
 
+/// Block erase clears 64KiB
+const BLOCK_SIZE: u32 = 64 * 1024;
+
 /// Flash trait implementation for W25Q series NOR flash chips
 impl<SPI: Transfer<u8>, CS: OutputPin> Flash for w25q::series25::Flash<SPI, CS>
 where
@@ -18,6 +21,9 @@ where
 {
     type Error = w25q::Error<SPI, CS>;
 
+    /// Sector erase clears 4KiB
+    const ERASE_SIZE: usize = 4096;
+
     fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
         w25q::series25::Flash::read(self, addr, buf)?;
         Ok(())
@@ -33,7 +39,18 @@ where
         Ok(())
     }
 
-    fn erase_all(&mut self, _count: usize) -> Result<(), Self::Error> {
-        w25q::series25::Flash::erase_all(self)
+    /// Erase the storage region with 64KiB block erases where aligned
+    fn erase_all(&mut self, len: u32) -> Result<(), Self::Error> {
+        let mut addr = 0;
+        while addr < len {
+            if addr.is_multiple_of(BLOCK_SIZE) && len - addr >= BLOCK_SIZE {
+                self.erase_block(addr)?;
+                addr += BLOCK_SIZE;
+            } else {
+                self.erase_sectors(addr, 1)?;
+                addr += Self::ERASE_SIZE as u32;
+            }
+        }
+        Ok(())
     }
 }